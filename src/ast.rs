@@ -1,26 +1,284 @@
-use crate::token::Token;
+use std::{fmt, rc::Rc};
+
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+use crate::token::{Span, Token, TokenType};
 
 pub trait AstNode {
     fn token_literal(&self) -> String;
     fn to_string(&self) -> String;
+    fn span(&self) -> Span;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Break(BreakStatement),
+    Continue(ContinueStatement),
+    Expression(ExpressionStatement),
+    For(ForStatement),
+    Let(LetStatement),
+    Return(ReturnStatement),
+    While(WhileStatement),
+}
+
+impl Statement {
+    fn node(&self) -> &dyn AstNode {
+        match self {
+            Statement::Break(statement) => statement,
+            Statement::Continue(statement) => statement,
+            Statement::Expression(statement) => statement,
+            Statement::For(statement) => statement,
+            Statement::Let(statement) => statement,
+            Statement::Return(statement) => statement,
+            Statement::While(statement) => statement,
+        }
+    }
+}
+
+impl AstNode for Statement {
+    fn token_literal(&self) -> String {
+        self.node().token_literal()
+    }
+
+    fn to_string(&self) -> String {
+        self.node().to_string()
+    }
+
+    fn span(&self) -> Span {
+        self.node().span()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Array(ArrayLiteral),
+    Assign(Assign),
+    Boolean(Boolean),
+    Call(Call),
+    Field(Field),
+    Float(Float),
+    Function(Function),
+    Identifier(Identifier),
+    /// Boxed because its two blocks make it much larger than the rest.
+    If(Box<If>),
+    Index(Index),
+    Infix(Infix),
+    Integer(Integer),
+    Interpolation(Interpolation),
+    Map(MapLiteral),
+    Prefix(Prefix),
+    Slice(Slice),
+    StringLiteral(StringLiteral),
+}
+
+impl Expression {
+    fn node(&self) -> &dyn AstNode {
+        match self {
+            Expression::Array(expression) => expression,
+            Expression::Assign(expression) => expression,
+            Expression::Boolean(expression) => expression,
+            Expression::Call(expression) => expression,
+            Expression::Field(expression) => expression,
+            Expression::Float(expression) => expression,
+            Expression::Function(expression) => expression,
+            Expression::Identifier(expression) => expression,
+            Expression::If(expression) => expression.as_ref(),
+            Expression::Index(expression) => expression,
+            Expression::Infix(expression) => expression,
+            Expression::Integer(expression) => expression,
+            Expression::Interpolation(expression) => expression,
+            Expression::Map(expression) => expression,
+            Expression::Prefix(expression) => expression,
+            Expression::Slice(expression) => expression,
+            Expression::StringLiteral(expression) => expression,
+        }
+    }
 }
 
-pub trait Expression: AstNode {}
-pub trait Statement: AstNode {}
+impl AstNode for Expression {
+    fn token_literal(&self) -> String {
+        self.node().token_literal()
+    }
+
+    fn to_string(&self) -> String {
+        self.node().to_string()
+    }
+
+    fn span(&self) -> Span {
+        self.node().span()
+    }
+}
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Program {
-    pub statements: Vec<Box<dyn Statement>>,
+    pub statements: Vec<Statement>,
 }
 
 impl Program {
-    pub fn new(statements: Vec<Box<dyn Statement>>) -> Program {
+    pub fn new(statements: Vec<Statement>) -> Program {
         Program { statements }
     }
+
+    /// An indented, one-node-per-line dump of the tree, used by the REPL's
+    /// `:ast` command.
+    pub fn to_tree(&self) -> String {
+        let mut out = "Program\n".to_owned();
+        for statement in &self.statements {
+            write_statement(&mut out, statement, 1);
+        }
+        return out;
+    }
+}
+
+fn write_line(out: &mut String, depth: usize, line: &str) {
+    out.push_str(&"  ".repeat(depth));
+    out.push_str(line);
+    out.push('\n');
+}
+
+fn write_statement(out: &mut String, statement: &Statement, depth: usize) {
+    match statement {
+        Statement::Break(_) => write_line(out, depth, "Break"),
+        Statement::Continue(_) => write_line(out, depth, "Continue"),
+        Statement::Expression(statement) => write_expression(out, &statement.expression, depth),
+        Statement::For(statement) => {
+            write_line(out, depth, &format!("For {}", statement.variable.value));
+            write_expression(out, &statement.iterable, depth + 1);
+            write_block(out, "Body", &statement.body, depth + 1);
+        }
+        Statement::Let(statement) => {
+            write_line(out, depth, &format!("Let {}", statement.name.value));
+            write_doc_comments(out, &statement.token, depth + 1);
+            write_expression(out, &statement.value, depth + 1);
+        }
+        Statement::Return(statement) => {
+            write_line(out, depth, "Return");
+            write_expression(out, &statement.return_value, depth + 1);
+        }
+        Statement::While(statement) => {
+            write_line(out, depth, "While");
+            write_expression(out, &statement.condition, depth + 1);
+            write_block(out, "Body", &statement.body, depth + 1);
+        }
+    }
+}
+
+fn write_doc_comments(out: &mut String, token: &Token, depth: usize) {
+    for line in &token.doc_comments {
+        write_line(out, depth, &format!("Doc {:?}", line));
+    }
+}
+
+fn write_block(out: &mut String, label: &str, block: &Block, depth: usize) {
+    write_line(out, depth, label);
+    for statement in &block.statements {
+        write_statement(out, statement, depth + 1);
+    }
+}
+
+fn write_expression(out: &mut String, expression: &Expression, depth: usize) {
+    match expression {
+        Expression::Array(array) => {
+            write_line(out, depth, "Array");
+            for element in &array.elements {
+                write_expression(out, element, depth + 1);
+            }
+        }
+        Expression::Assign(assign) => {
+            write_line(out, depth, "Assign");
+            write_expression(out, &assign.target, depth + 1);
+            write_expression(out, &assign.value, depth + 1);
+        }
+        Expression::Boolean(boolean) => {
+            write_line(out, depth, &format!("Boolean {}", boolean.value))
+        }
+        Expression::Call(call) => {
+            write_line(out, depth, "Call");
+            write_expression(out, &call.function, depth + 1);
+            for argument in &call.arguments {
+                write_expression(out, argument, depth + 1);
+            }
+        }
+        Expression::Field(field) => {
+            write_line(out, depth, &format!("Field {}", field.field.value));
+            write_expression(out, &field.left, depth + 1);
+        }
+        Expression::Float(float) => write_line(out, depth, &format!("Float {:?}", float.value)),
+        Expression::Function(function) => {
+            let parameters = function
+                .parameters
+                .iter()
+                .map(|p| p.value.clone())
+                .collect::<Vec<String>>()
+                .join(", ");
+            write_line(out, depth, &format!("Proc({})", parameters));
+            write_doc_comments(out, &function.token, depth + 1);
+            for statement in &function.body.statements {
+                write_statement(out, statement, depth + 1);
+            }
+        }
+        Expression::Identifier(identifier) => {
+            write_line(out, depth, &format!("Identifier {}", identifier.value))
+        }
+        Expression::If(if_expression) => {
+            write_line(out, depth, "If");
+            write_expression(out, &if_expression.condition, depth + 1);
+            write_block(out, "Then", &if_expression.consequence, depth + 1);
+            if let Some(alternative) = &if_expression.alternative {
+                write_block(out, "Else", alternative, depth + 1);
+            }
+        }
+        Expression::Index(index) => {
+            write_line(out, depth, "Index");
+            write_expression(out, &index.left, depth + 1);
+            write_expression(out, &index.index, depth + 1);
+        }
+        Expression::Infix(infix) => {
+            write_line(out, depth, &format!("Infix {}", infix.operator));
+            write_expression(out, &infix.left, depth + 1);
+            write_expression(out, &infix.right, depth + 1);
+        }
+        Expression::Integer(integer) => {
+            write_line(out, depth, &format!("Integer {}", integer.value))
+        }
+        Expression::Interpolation(interpolation) => {
+            write_line(out, depth, "Interpolation");
+            for part in &interpolation.parts {
+                write_expression(out, part, depth + 1);
+            }
+        }
+        Expression::Map(map) => {
+            write_line(out, depth, "Map");
+            for (key, value) in &map.pairs {
+                write_line(out, depth + 1, "Pair");
+                write_expression(out, key, depth + 2);
+                write_expression(out, value, depth + 2);
+            }
+        }
+        Expression::Prefix(prefix) => {
+            write_line(out, depth, &format!("Prefix {}", prefix.operator));
+            write_expression(out, &prefix.right, depth + 1);
+        }
+        Expression::Slice(slice) => {
+            write_line(out, depth, "Slice");
+            write_expression(out, &slice.left, depth + 1);
+            for (label, bound) in [("Start", &slice.start), ("End", &slice.end)] {
+                if let Some(bound) = bound {
+                    write_line(out, depth + 1, label);
+                    write_expression(out, bound, depth + 2);
+                }
+            }
+        }
+        Expression::StringLiteral(string) => {
+            write_line(out, depth, &format!("String {:?}", string.value))
+        }
+    }
 }
 
 impl AstNode for Program {
     fn token_literal(&self) -> String {
-        if self.statements.len() > 0 {
+        if !self.statements.is_empty() {
             self.statements
                 .iter()
                 .map(|s| s.token_literal())
@@ -32,7 +290,7 @@ impl AstNode for Program {
     }
 
     fn to_string(&self) -> String {
-        if self.statements.len() > 0 {
+        if !self.statements.is_empty() {
             self.statements
                 .iter()
                 .map(|s| s.to_string())
@@ -42,9 +300,17 @@ impl AstNode for Program {
             "".to_owned()
         }
     }
+
+    fn span(&self) -> Span {
+        match (self.statements.first(), self.statements.last()) {
+            (Some(first), Some(last)) => first.span().merge(last.span()),
+            _ => Span::default(),
+        }
+    }
 }
 
 /* IDENTIFIER */
+#[derive(Debug, Clone, PartialEq)]
 pub struct Identifier {
     pub token: Token,
     pub value: String,
@@ -64,16 +330,19 @@ impl AstNode for Identifier {
     fn to_string(&self) -> String {
         self.value.clone()
     }
-}
 
-impl Expression for Identifier {}
+    fn span(&self) -> Span {
+        self.token.span
+    }
+}
 /* END IDENTIFIER */
 
 /* LET STATEMENT */
+#[derive(Debug, Clone, PartialEq)]
 pub struct LetStatement {
     pub token: Token,
-    pub name: Option<Identifier>,
-    pub value: Option<Box<dyn Expression>>,
+    pub name: Identifier,
+    pub value: Box<Expression>,
 }
 
 impl AstNode for LetStatement {
@@ -85,19 +354,22 @@ impl AstNode for LetStatement {
         return format!(
             "{} {} = {}",
             self.token_literal(),
-            self.name.as_ref().unwrap().value,
-            self.value.as_ref().unwrap().to_string()
+            self.name.value,
+            self.value.to_string()
         );
     }
-}
 
-impl Statement for LetStatement {}
+    fn span(&self) -> Span {
+        self.token.span.merge(self.value.span())
+    }
+}
 /* END LET STATEMENT */
 
 /* RETURN STATEMENT */
+#[derive(Debug, Clone, PartialEq)]
 pub struct ReturnStatement {
     pub token: Token,
-    pub return_value: Option<Box<dyn Expression>>,
+    pub return_value: Box<Expression>,
 }
 
 impl AstNode for ReturnStatement {
@@ -106,21 +378,20 @@ impl AstNode for ReturnStatement {
     }
 
     fn to_string(&self) -> String {
-        return format!(
-            "{} {}",
-            self.token_literal(),
-            self.return_value.as_ref().unwrap().to_string()
-        );
+        return format!("{} {}", self.token_literal(), self.return_value.to_string());
     }
-}
 
-impl Statement for ReturnStatement {}
+    fn span(&self) -> Span {
+        self.token.span.merge(self.return_value.span())
+    }
+}
 /* END RETURN STATEMENT */
 
 /* EXPRESSION STATEMENT */
+#[derive(Debug, Clone, PartialEq)]
 pub struct ExpressionStatement {
     pub token: Token,
-    pub expression: Option<Box<dyn Expression>>,
+    pub expression: Box<Expression>,
 }
 
 impl AstNode for ExpressionStatement {
@@ -129,17 +400,146 @@ impl AstNode for ExpressionStatement {
     }
 
     fn to_string(&self) -> String {
-        self.expression.as_ref().unwrap().to_string()
+        self.expression.to_string()
     }
-}
 
-impl Statement for ExpressionStatement {}
+    fn span(&self) -> Span {
+        self.expression.span()
+    }
+}
 /* END EXPRESSION STATEMENT */
 
+/* WHILE STATEMENT */
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhileStatement {
+    pub token: Token,
+    pub condition: Box<Expression>,
+    pub body: Block,
+}
+
+impl AstNode for WhileStatement {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn to_string(&self) -> String {
+        return format!(
+            "{} {} {}",
+            self.token_literal(),
+            self.condition.to_string(),
+            self.body.to_string()
+        );
+    }
+
+    fn span(&self) -> Span {
+        self.token.span.merge(self.body.span)
+    }
+}
+/* END WHILE STATEMENT */
+
+/* FOR STATEMENT */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForStatement {
+    pub token: Token,
+    pub variable: Identifier,
+    pub iterable: Box<Expression>,
+    pub body: Block,
+}
+
+impl AstNode for ForStatement {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn to_string(&self) -> String {
+        return format!(
+            "{} {} in {} {}",
+            self.token_literal(),
+            self.variable.value,
+            self.iterable.to_string(),
+            self.body.to_string()
+        );
+    }
+
+    fn span(&self) -> Span {
+        self.token.span.merge(self.body.span)
+    }
+}
+/* END FOR STATEMENT */
+
+/* BREAK STATEMENT */
+#[derive(Debug, Clone, PartialEq)]
+pub struct BreakStatement {
+    pub token: Token,
+}
+
+impl AstNode for BreakStatement {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn to_string(&self) -> String {
+        self.token_literal()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span
+    }
+}
+/* END BREAK STATEMENT */
+
+/* CONTINUE STATEMENT */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContinueStatement {
+    pub token: Token,
+}
+
+impl AstNode for ContinueStatement {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn to_string(&self) -> String {
+        self.token_literal()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span
+    }
+}
+/* END CONTINUE STATEMENT */
+
 /* INTEGER */
+#[derive(Debug, Clone, PartialEq)]
 pub struct Integer {
     pub token: Token,
-    pub value: Option<isize>,
+    pub value: IntegerValue,
+}
+
+/// An integer literal's value, resolved at parse time so that evaluating it
+/// only allocates when it does not fit in an `isize`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IntegerValue {
+    Small(isize),
+    Big(BigInt),
+}
+
+impl IntegerValue {
+    pub fn from_big_int(value: BigInt) -> IntegerValue {
+        return match value.to_isize() {
+            Some(value) => IntegerValue::Small(value),
+            None => IntegerValue::Big(value),
+        };
+    }
+}
+
+impl fmt::Display for IntegerValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            IntegerValue::Small(value) => write!(f, "{}", value),
+            IntegerValue::Big(value) => write!(f, "{}", value),
+        };
+    }
 }
 
 impl AstNode for Integer {
@@ -148,18 +548,43 @@ impl AstNode for Integer {
     }
 
     fn to_string(&self) -> String {
-        return self.value.as_ref().unwrap().to_string();
+        return self.value.to_string();
     }
-}
 
-impl Expression for Integer {}
+    fn span(&self) -> Span {
+        self.token.span
+    }
+}
 /* END INTEGER */
 
+/* FLOAT */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Float {
+    pub token: Token,
+    pub value: f64,
+}
+
+impl AstNode for Float {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn to_string(&self) -> String {
+        return format!("{:?}", self.value);
+    }
+
+    fn span(&self) -> Span {
+        self.token.span
+    }
+}
+/* END FLOAT */
+
 /* PREFIX */
+#[derive(Debug, Clone, PartialEq)]
 pub struct Prefix {
     pub token: Token,
     pub operator: String,
-    pub right: Option<Box<dyn Expression>>,
+    pub right: Box<Expression>,
 }
 
 impl AstNode for Prefix {
@@ -168,21 +593,22 @@ impl AstNode for Prefix {
     }
 
     fn to_string(&self) -> String {
-        return format!(
-            "{}{}",
-            self.operator,
-            self.right.as_ref().unwrap().to_string()
-        );
+        return format!("({}{})", self.operator, self.right.to_string());
+    }
+
+    fn span(&self) -> Span {
+        self.token.span.merge(self.right.span())
     }
 }
 /* END PREFIX */
 
 /* INFIX */
+#[derive(Debug, Clone, PartialEq)]
 pub struct Infix {
     pub token: Token,
-    pub left: Box<dyn Expression>,
+    pub left: Box<Expression>,
     pub operator: String,
-    pub right: Option<Box<dyn Expression>>,
+    pub right: Box<Expression>,
 }
 
 impl AstNode for Infix {
@@ -192,21 +618,24 @@ impl AstNode for Infix {
 
     fn to_string(&self) -> String {
         return format!(
-            "{} {} {}",
+            "({} {} {})",
             self.left.to_string(),
             self.operator,
-            self.right.as_ref().unwrap().to_string()
+            self.right.to_string()
         );
     }
-}
 
-impl Expression for Infix {}
+    fn span(&self) -> Span {
+        self.left.span().merge(self.right.span())
+    }
+}
 /* END INFIX */
 
 /* BOOLEAN */
+#[derive(Debug, Clone, PartialEq)]
 pub struct Boolean {
     pub token: Token,
-    pub value: Option<bool>,
+    pub value: bool,
 }
 
 impl AstNode for Boolean {
@@ -215,17 +644,21 @@ impl AstNode for Boolean {
     }
 
     fn to_string(&self) -> String {
-        return self.value.as_ref().unwrap().to_string();
+        return self.value.to_string();
     }
-}
 
-impl Expression for Boolean {}
+    fn span(&self) -> Span {
+        self.token.span
+    }
+}
 /* END BOOLEAN */
 
 /* BLOCK STATEMENT */
+#[derive(Debug, Clone, PartialEq)]
 pub struct Block {
     pub token: Token,
-    pub statements: Vec<Box<dyn Statement>>,
+    pub statements: Vec<Statement>,
+    pub span: Span,
 }
 
 impl AstNode for Block {
@@ -233,26 +666,27 @@ impl AstNode for Block {
         self.token.literal.clone()
     }
     fn to_string(&self) -> String {
-        return format!(
-            "{}",
-            self.statements
-                .iter()
-                .map(|s| s.to_string())
-                .collect::<Vec<String>>()
-                .join("")
-        );
+        return self
+            .statements
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>()
+            .join("");
     }
-}
 
-impl Statement for Block {}
+    fn span(&self) -> Span {
+        self.span
+    }
+}
 /* END BLOCK STATEMENT */
 
 /* IF EXPRESSION */
+#[derive(Debug, Clone, PartialEq)]
 pub struct If {
     pub token: Token,
-    pub condition: Option<Box<dyn Expression>>,
-    pub consequence: Option<Box<Block>>,
-    pub alternative: Option<Box<Block>>,
+    pub condition: Box<Expression>,
+    pub consequence: Block,
+    pub alternative: Option<Block>,
 }
 
 impl AstNode for If {
@@ -262,29 +696,33 @@ impl AstNode for If {
     fn to_string(&self) -> String {
         let mut out = format!(
             "si {} {}",
-            self.condition.as_ref().unwrap().to_string(),
-            self.consequence.as_ref().unwrap().to_string()
+            self.condition.to_string(),
+            self.consequence.to_string()
         );
-        if self.alternative.is_some() {
-            out = format!(
-                "{} sino {}",
-                out,
-                self.alternative.as_ref().unwrap().to_string()
-            );
+        if let Some(alternative) = &self.alternative {
+            out = format!("{} sino {}", out, alternative.to_string());
         }
 
         return out;
     }
-}
 
-impl Expression for If {}
+    fn span(&self) -> Span {
+        match &self.alternative {
+            Some(alternative) => self.token.span.merge(alternative.span),
+            None => self.token.span.merge(self.consequence.span),
+        }
+    }
+}
 /* END IF EXPRESSION */
 
 /* FUNCTION EXPRESSION */
+#[derive(Debug, Clone, PartialEq)]
 pub struct Function {
     pub token: Token,
     pub parameters: Vec<Identifier>,
-    pub body: Option<Block>,
+    /// Shared with every closure made from this literal, so evaluating it
+    /// does not copy the body.
+    pub body: Rc<Block>,
 }
 
 impl AstNode for Function {
@@ -303,19 +741,23 @@ impl AstNode for Function {
             "{}({}) {}",
             self.token_literal(),
             param_list,
-            self.body.as_ref().unwrap().to_string()
+            self.body.to_string()
         );
     }
-}
 
-impl Expression for Function {}
+    fn span(&self) -> Span {
+        self.token.span.merge(self.body.span)
+    }
+}
 /* END FUNCTION EXPRESSION */
 
 /* CALL EXPRESSION */
+#[derive(Debug, Clone, PartialEq)]
 pub struct Call {
     pub token: Token,
-    pub function: Box<dyn Expression>,
-    pub arguments: Option<Vec<Box<dyn Expression>>>,
+    pub function: Box<Expression>,
+    pub arguments: Vec<Expression>,
+    pub span: Span,
 }
 
 impl AstNode for Call {
@@ -326,20 +768,21 @@ impl AstNode for Call {
     fn to_string(&self) -> String {
         let args = self
             .arguments
-            .as_ref()
-            .unwrap()
             .iter()
             .map(|a| a.to_string())
             .collect::<Vec<String>>()
             .join(", ");
         return format!("{}({})", self.function.to_string(), args);
     }
-}
 
-impl Expression for Call {}
+    fn span(&self) -> Span {
+        self.span
+    }
+}
 /* END CALL EXPRESSION */
 
 /* STRING LITERAL */
+#[derive(Debug, Clone, PartialEq)]
 pub struct StringLiteral {
     pub token: Token,
     pub value: String,
@@ -353,7 +796,227 @@ impl AstNode for StringLiteral {
     fn to_string(&self) -> String {
         return self.value.clone();
     }
-}
 
-impl Expression for StringLiteral {}
+    fn span(&self) -> Span {
+        self.token.span
+    }
+}
 /* END STRING LITERAL */
+
+/* INTERPOLATION */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interpolation {
+    pub token: Token,
+    /// The literal segments of the string and the expressions embedded
+    /// between them, in source order.
+    pub parts: Vec<Expression>,
+    pub span: Span,
+}
+
+impl AstNode for Interpolation {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn to_string(&self) -> String {
+        let parts = self
+            .parts
+            .iter()
+            .map(|part| match part {
+                Expression::StringLiteral(segment)
+                    if segment.token.token_type != TokenType::STRING =>
+                {
+                    segment.value.clone()
+                }
+                expression => format!("${{{}}}", expression.to_string()),
+            })
+            .collect::<Vec<String>>()
+            .join("");
+        return format!("\"{}\"", parts);
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+/* END INTERPOLATION */
+
+/* ARRAY LITERAL */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayLiteral {
+    pub token: Token,
+    pub elements: Vec<Expression>,
+    pub span: Span,
+}
+
+impl AstNode for ArrayLiteral {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn to_string(&self) -> String {
+        let elements = self
+            .elements
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        return format!("[{}]", elements);
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+/* END ARRAY LITERAL */
+
+/* INDEX EXPRESSION */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Index {
+    pub token: Token,
+    pub left: Box<Expression>,
+    pub index: Box<Expression>,
+    pub span: Span,
+}
+
+impl AstNode for Index {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn to_string(&self) -> String {
+        return format!("({}[{}])", self.left.to_string(), self.index.to_string());
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+/* END INDEX EXPRESSION */
+
+/* FIELD EXPRESSION */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub token: Token,
+    pub left: Box<Expression>,
+    pub field: Identifier,
+}
+
+impl AstNode for Field {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn to_string(&self) -> String {
+        return format!("({}.{})", self.left.to_string(), self.field.value);
+    }
+
+    fn span(&self) -> Span {
+        self.left.span().merge(self.field.span())
+    }
+}
+/* END FIELD EXPRESSION */
+
+/* SLICE EXPRESSION */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slice {
+    pub token: Token,
+    pub left: Box<Expression>,
+    /// `None` when the bound is left out, as in `a[:2]` or `a[1:]`.
+    pub start: Option<Box<Expression>>,
+    pub end: Option<Box<Expression>>,
+    pub span: Span,
+}
+
+impl AstNode for Slice {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn to_string(&self) -> String {
+        let bound = |bound: &Option<Box<Expression>>| match bound {
+            Some(expression) => expression.to_string(),
+            None => "".to_owned(),
+        };
+        return format!(
+            "({}[{}:{}])",
+            self.left.to_string(),
+            bound(&self.start),
+            bound(&self.end)
+        );
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+/* END SLICE EXPRESSION */
+
+/* MAP LITERAL */
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapLiteral {
+    pub token: Token,
+    pub pairs: Vec<(Expression, Expression)>,
+    pub span: Span,
+}
+
+impl AstNode for MapLiteral {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn to_string(&self) -> String {
+        let pairs = self
+            .pairs
+            .iter()
+            .map(|(key, value)| format!("{}: {}", key.to_string(), value.to_string()))
+            .collect::<Vec<String>>()
+            .join(", ");
+        return format!("{{{}}}", pairs);
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+/* END MAP LITERAL */
+
+/* ASSIGN EXPRESSION */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assign {
+    pub token: Token,
+    pub target: Box<Expression>,
+    pub value: Box<Expression>,
+}
+
+impl Assign {
+    /// The arithmetic operator of a compound assignment such as `+=`, or
+    /// `None` for a plain `=`.
+    pub fn operator(&self) -> Option<&str> {
+        return self
+            .token
+            .literal
+            .strip_suffix('=')
+            .filter(|operator| !operator.is_empty());
+    }
+}
+
+impl AstNode for Assign {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn to_string(&self) -> String {
+        return format!(
+            "({} {} {})",
+            self.target.to_string(),
+            self.token.literal,
+            self.value.to_string()
+        );
+    }
+
+    fn span(&self) -> Span {
+        self.target.span().merge(self.value.span())
+    }
+}
+/* END ASSIGN EXPRESSION */
@@ -0,0 +1,186 @@
+use crate::object::{BuiltinFunction, HashKey, Object};
+
+/// Every builtin, by name. A static table, so looking one up allocates
+/// nothing.
+pub const BUILTINS: [(&str, BuiltinFunction); 10] = [
+    ("first", first),
+    ("has", has),
+    ("keys", keys),
+    ("last", last),
+    ("len", len),
+    ("pop", pop),
+    ("push", push),
+    ("remove", remove),
+    ("rest", rest),
+    ("values", values),
+];
+
+pub fn lookup_builtin(name: &str) -> Option<Object> {
+    let (name, function) = BUILTINS.iter().find(|(builtin, _)| *builtin == name)?;
+    return Some(Object::Builtin {
+        name,
+        function: *function,
+    });
+}
+
+fn len(arguments: Vec<Object>) -> Object {
+    if let Some(error) = check_argument_count(&arguments, 1) {
+        return error;
+    }
+
+    match &arguments[0] {
+        Object::Array(elements) => Object::Integer(elements.len() as isize),
+        Object::Map(pairs) => Object::Integer(pairs.len() as isize),
+        Object::Range { start, end } => Object::Integer(end.saturating_sub(*start).max(0)),
+        Object::String(value) => Object::Integer(value.chars().count() as isize),
+        other => unsupported_argument("len", other),
+    }
+}
+
+fn first(arguments: Vec<Object>) -> Object {
+    if let Some(error) = check_argument_count(&arguments, 1) {
+        return error;
+    }
+
+    match &arguments[0] {
+        Object::Array(elements) => match elements.first() {
+            Some(element) => element.clone(),
+            None => Object::Error("first: the array is empty".to_owned()),
+        },
+        other => unsupported_argument("first", other),
+    }
+}
+
+fn last(arguments: Vec<Object>) -> Object {
+    if let Some(error) = check_argument_count(&arguments, 1) {
+        return error;
+    }
+
+    match &arguments[0] {
+        Object::Array(elements) => match elements.last() {
+            Some(element) => element.clone(),
+            None => Object::Error("last: the array is empty".to_owned()),
+        },
+        other => unsupported_argument("last", other),
+    }
+}
+
+/// Every element but the first; empty for an empty array.
+fn rest(arguments: Vec<Object>) -> Object {
+    if let Some(error) = check_argument_count(&arguments, 1) {
+        return error;
+    }
+
+    match &arguments[0] {
+        Object::Array(elements) => Object::array(elements.iter().skip(1).cloned().collect()),
+        other => unsupported_argument("rest", other),
+    }
+}
+
+/// Returns a new array with the value appended; the argument is unchanged.
+fn push(arguments: Vec<Object>) -> Object {
+    if let Some(error) = check_argument_count(&arguments, 2) {
+        return error;
+    }
+
+    match &arguments[0] {
+        Object::Array(elements) => {
+            let mut pushed = Vec::with_capacity(elements.len() + 1);
+            pushed.extend_from_slice(elements);
+            pushed.push(arguments[1].clone());
+            Object::array(pushed)
+        }
+        other => unsupported_argument("push", other),
+    }
+}
+
+/// Returns a new array without the last element; the argument is unchanged.
+fn pop(arguments: Vec<Object>) -> Object {
+    if let Some(error) = check_argument_count(&arguments, 1) {
+        return error;
+    }
+
+    match &arguments[0] {
+        Object::Array(elements) if elements.is_empty() => {
+            Object::Error("pop: the array is empty".to_owned())
+        }
+        Object::Array(elements) => Object::array(elements[..elements.len() - 1].to_vec()),
+        other => unsupported_argument("pop", other),
+    }
+}
+
+/// The keys of a map, in the order the map iterates them.
+fn keys(arguments: Vec<Object>) -> Object {
+    if let Some(error) = check_argument_count(&arguments, 1) {
+        return error;
+    }
+
+    match &arguments[0] {
+        Object::Map(pairs) => Object::array(pairs.keys().map(|k| k.to_object()).collect()),
+        other => unsupported_argument("keys", other),
+    }
+}
+
+fn values(arguments: Vec<Object>) -> Object {
+    if let Some(error) = check_argument_count(&arguments, 1) {
+        return error;
+    }
+
+    match &arguments[0] {
+        Object::Map(pairs) => Object::array(pairs.values().cloned().collect()),
+        other => unsupported_argument("values", other),
+    }
+}
+
+fn has(arguments: Vec<Object>) -> Object {
+    if let Some(error) = check_argument_count(&arguments, 2) {
+        return error;
+    }
+
+    match &arguments[0] {
+        Object::Map(pairs) => match HashKey::from_object(&arguments[1]) {
+            Ok(key) => Object::Boolean(pairs.contains_key(&key)),
+            Err(error) => error,
+        },
+        other => unsupported_argument("has", other),
+    }
+}
+
+/// Returns a new map without the key; the argument is unchanged. Removing a
+/// key that is not there is not an error.
+fn remove(arguments: Vec<Object>) -> Object {
+    if let Some(error) = check_argument_count(&arguments, 2) {
+        return error;
+    }
+
+    match &arguments[0] {
+        Object::Map(pairs) => match HashKey::from_object(&arguments[1]) {
+            Ok(key) => {
+                let mut pairs = pairs.as_ref().clone();
+                pairs.remove(&key);
+                Object::map(pairs)
+            }
+            Err(error) => error,
+        },
+        other => unsupported_argument("remove", other),
+    }
+}
+
+fn check_argument_count(arguments: &[Object], expected: usize) -> Option<Object> {
+    if arguments.len() == expected {
+        return None;
+    }
+    return Some(Object::Error(format!(
+        "wrong number of arguments: expected {}, got {}",
+        expected,
+        arguments.len()
+    )));
+}
+
+fn unsupported_argument(name: &str, argument: &Object) -> Object {
+    return Object::Error(format!(
+        "argument to `{}` not supported, got {:?}",
+        name,
+        argument.object_type()
+    ));
+}
@@ -0,0 +1,257 @@
+use colored::{Color, Colorize};
+
+use crate::token::Span;
+
+// Only errors are emitted so far; the other levels are for future passes.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    ERROR,
+    WARNING,
+    NOTE,
+}
+
+impl Severity {
+    fn name(&self) -> &'static str {
+        match self {
+            Severity::ERROR => "error",
+            Severity::WARNING => "warning",
+            Severity::NOTE => "note",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Severity::ERROR => Color::Red,
+            Severity::WARNING => Color::Yellow,
+            Severity::NOTE => Color::Cyan,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+impl Label {
+    pub fn new(span: Span, message: &str) -> Label {
+        return Label {
+            span,
+            message: message.to_owned(),
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub message: String,
+    pub primary: Label,
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: String, span: Span) -> Diagnostic {
+        return Diagnostic {
+            severity,
+            code: None,
+            message,
+            primary: Label::new(span, ""),
+            secondary: vec![],
+            notes: vec![],
+            help: None,
+        };
+    }
+
+    pub fn error(message: String, span: Span) -> Diagnostic {
+        return Diagnostic::new(Severity::ERROR, message, span);
+    }
+
+    pub fn with_code(mut self, code: &str) -> Diagnostic {
+        self.code = Some(code.to_owned());
+        return self;
+    }
+
+    pub fn with_primary_label(mut self, message: &str) -> Diagnostic {
+        self.primary.message = message.to_owned();
+        return self;
+    }
+
+    pub fn with_label(mut self, span: Span, message: &str) -> Diagnostic {
+        self.secondary.push(Label::new(span, message));
+        return self;
+    }
+
+    pub fn with_note(mut self, note: &str) -> Diagnostic {
+        self.notes.push(note.to_owned());
+        return self;
+    }
+
+    pub fn with_help(mut self, help: &str) -> Diagnostic {
+        self.help = Some(help.to_owned());
+        return self;
+    }
+
+    /// Renders the diagnostic the way it is printed to the terminal: a
+    /// header, the `file:line:column` it points at and every source line
+    /// touched by a label, underlined with `^` (primary) or `-` (secondary).
+    pub fn render(&self, source: &str, file_name: &str) -> String {
+        let color = self.severity.color();
+        let lines: Vec<&str> = source.split('\n').collect();
+
+        let mut labels = vec![(&self.primary, '^', color)];
+        for label in &self.secondary {
+            labels.push((label, '-', Color::Blue));
+        }
+
+        let mut line_numbers: Vec<usize> =
+            labels.iter().map(|(l, _, _)| l.span.start.line).collect();
+        line_numbers.sort_unstable();
+        line_numbers.dedup();
+
+        let gutter_width = line_numbers.last().unwrap().to_string().len();
+        let gutter = " ".repeat(gutter_width);
+        let bar = "|".blue().bold();
+
+        let header = match &self.code {
+            Some(code) => format!("{}[{}]", self.severity.name(), code),
+            None => self.severity.name().to_owned(),
+        };
+        let mut out = format!(
+            "{}{} {}\n",
+            header.color(color).bold(),
+            ":".bold(),
+            self.message.bold()
+        );
+        out += &format!(
+            "{}{} {}:{}:{}\n",
+            gutter,
+            "-->".blue().bold(),
+            file_name,
+            self.primary.span.start.line,
+            self.primary.span.start.column
+        );
+        out += &format!("{} {}\n", gutter, bar);
+
+        for line_number in line_numbers {
+            let line = lines
+                .get(line_number - 1)
+                .copied()
+                .unwrap_or("")
+                .trim_end_matches('\r');
+            out += &format!(
+                "{} {} {}\n",
+                format!("{:>width$}", line_number, width = gutter_width)
+                    .blue()
+                    .bold(),
+                bar,
+                line
+            );
+
+            for (label, marker, label_color) in &labels {
+                if label.span.start.line != line_number {
+                    continue;
+                }
+
+                let line_length = line.chars().count();
+                let start = label.span.start.column - 1;
+                let end = if label.span.end.line == line_number {
+                    label.span.end.column - 1
+                } else {
+                    line_length
+                };
+                let width = end.saturating_sub(start).max(1);
+
+                let underline = format!(
+                    "{}{}",
+                    marker.to_string().repeat(width),
+                    label_message(label)
+                );
+                out += &format!(
+                    "{} {} {}{}\n",
+                    gutter,
+                    bar,
+                    " ".repeat(start),
+                    underline.color(*label_color).bold()
+                );
+            }
+        }
+
+        if !self.notes.is_empty() || self.help.is_some() {
+            out += &format!("{} {}\n", gutter, bar);
+        }
+        for note in &self.notes {
+            out += &format!(
+                "{} {} {}: {}\n",
+                gutter,
+                "=".blue().bold(),
+                "note".bold(),
+                note
+            );
+        }
+        if let Some(help) = &self.help {
+            out += &format!(
+                "{} {} {}: {}\n",
+                gutter,
+                "=".blue().bold(),
+                "help".bold(),
+                help
+            );
+        }
+
+        return out;
+    }
+}
+
+fn label_message(label: &Label) -> String {
+    if label.message.is_empty() {
+        return "".to_owned();
+    }
+    return format!(" {}", label.message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::Position;
+
+    fn span(line: usize, start: usize, end: usize) -> Span {
+        return Span::new(Position::new(0, line, start), Position::new(0, line, end));
+    }
+
+    #[test]
+    fn test_render() {
+        colored::control::set_override(false);
+
+        let source = "declare x = 5;\ndeclare y = x + true;";
+        let diagnostic = Diagnostic::error(
+            "type mismatch: INTEGER + BOOLEAN".to_owned(),
+            span(2, 13, 21),
+        )
+        .with_code("E0100")
+        .with_primary_label("cannot add these values")
+        .with_label(span(1, 9, 10), "x is declared here")
+        .with_note("both operands of + must have the same type")
+        .with_help("compare the values with == instead");
+
+        let expected = "\
+error[E0100]: type mismatch: INTEGER + BOOLEAN
+ --> main.nel:2:13
+  |
+1 | declare x = 5;
+  |         - x is declared here
+2 | declare y = x + true;
+  |             ^^^^^^^^ cannot add these values
+  |
+  = note: both operands of + must have the same type
+  = help: compare the values with == instead
+";
+
+        assert_eq!(diagnostic.render(source, "main.nel"), expected);
+    }
+}
@@ -0,0 +1,64 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::object::Object;
+
+pub type Env = Rc<RefCell<Environment>>;
+
+pub struct Environment {
+    store: HashMap<String, Object>,
+    outer: Option<Env>,
+}
+
+impl Environment {
+    pub fn new() -> Env {
+        return Rc::new(RefCell::new(Environment {
+            store: HashMap::new(),
+            outer: None,
+        }));
+    }
+
+    pub fn new_enclosed(outer: &Env) -> Env {
+        return Rc::new(RefCell::new(Environment {
+            store: HashMap::new(),
+            outer: Some(Rc::clone(outer)),
+        }));
+    }
+
+    pub fn get(&self, name: &str) -> Option<Object> {
+        match self.store.get(name) {
+            Some(value) => Some(value.clone()),
+            None => match &self.outer {
+                Some(outer) => outer.borrow().get(name),
+                None => None,
+            },
+        }
+    }
+
+    /// The bindings declared directly in this scope, sorted by name.
+    pub fn bindings(&self) -> Vec<(String, Object)> {
+        let mut bindings: Vec<(String, Object)> = self
+            .store
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        return bindings;
+    }
+
+    pub fn set(&mut self, name: String, value: Object) {
+        self.store.insert(name, value);
+    }
+
+    /// Runs `update` on the binding for `name` in the scope that declared
+    /// it, without copying the value out. Returns `None` when no enclosing
+    /// scope declares it.
+    pub fn update<T>(&mut self, name: &str, update: impl FnOnce(&mut Object) -> T) -> Option<T> {
+        if let Some(binding) = self.store.get_mut(name) {
+            return Some(update(binding));
+        }
+        return match &self.outer {
+            Some(outer) => outer.borrow_mut().update(name, update),
+            None => None,
+        };
+    }
+}
@@ -0,0 +1,1499 @@
+use std::{cell::Cell, collections::BTreeMap, rc::Rc};
+
+use num_bigint::BigInt;
+use num_traits::{ToPrimitive, Zero};
+
+use crate::{
+    ast::{
+        Assign, Block, Call, Expression, ForStatement, If, IntegerValue, LetStatement, MapLiteral,
+        Program, Slice, Statement, WhileStatement,
+    },
+    builtins::lookup_builtin,
+    environment::{Env, Environment},
+    object::{HashKey, Object},
+};
+
+/// How deeply procs may call each other before evaluation stops with an
+/// error, instead of overflowing the native stack.
+const MAX_CALL_DEPTH: usize = 10_000;
+
+/// The native stack a thread needs to reach `MAX_CALL_DEPTH` nested calls.
+/// A call takes about 8 KiB of stack in debug builds and 2 KiB in release
+/// builds, so this leaves room for calls nested inside larger expressions.
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+thread_local! {
+    static CALL_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+pub fn evaluate(program: &Program, env: &Env) -> Object {
+    let mut result = Object::Null;
+
+    for statement in &program.statements {
+        result = evaluate_statement(statement, env);
+
+        match result {
+            Object::Return(value) => return *value,
+            Object::Error(_) => return result,
+            _ => {}
+        }
+    }
+
+    return result;
+}
+
+fn evaluate_statement(statement: &Statement, env: &Env) -> Object {
+    match statement {
+        Statement::Break(_) => Object::Break,
+        Statement::Continue(_) => Object::Continue,
+        Statement::Expression(statement) => evaluate_expression(&statement.expression, env),
+        Statement::For(statement) => evaluate_for_statement(statement, env),
+        Statement::Let(statement) => evaluate_let_statement(statement, env),
+        Statement::Return(statement) => {
+            let value = evaluate_expression(&statement.return_value, env);
+            if value.is_abrupt() {
+                return value;
+            }
+            Object::Return(Box::new(value))
+        }
+        Statement::While(statement) => evaluate_while_statement(statement, env),
+    }
+}
+
+fn evaluate_expression(expression: &Expression, env: &Env) -> Object {
+    match expression {
+        Expression::Array(array) => match evaluate_expressions(&array.elements, env) {
+            Ok(elements) => Object::array(elements),
+            Err(error) => error,
+        },
+        Expression::Assign(assign) => evaluate_assign_expression(assign, env),
+        Expression::Boolean(boolean) => Object::Boolean(boolean.value),
+        Expression::Call(call) => evaluate_call(call, env),
+        Expression::Field(field) => {
+            let left = evaluate_expression(&field.left, env);
+            if left.is_abrupt() {
+                return left;
+            }
+            evaluate_field_expression(left, &field.field.value)
+        }
+        Expression::Float(float) => Object::Float(float.value),
+        Expression::Function(function) => Object::Function {
+            parameters: function.parameters.clone(),
+            body: Rc::clone(&function.body),
+            env: Rc::clone(env),
+        },
+        Expression::Identifier(identifier) => match env.borrow().get(&identifier.value) {
+            Some(value) => value,
+            None => match lookup_builtin(&identifier.value) {
+                Some(builtin) => builtin,
+                None => Object::Error(format!("identifier not found: {}", identifier.value)),
+            },
+        },
+        Expression::If(if_expression) => evaluate_if_expression(if_expression, env),
+        Expression::Index(index) => {
+            let left = evaluate_expression(&index.left, env);
+            if left.is_abrupt() {
+                return left;
+            }
+            let index = evaluate_expression(&index.index, env);
+            if index.is_abrupt() {
+                return index;
+            }
+            evaluate_index_expression(left, index)
+        }
+        Expression::Infix(infix) => {
+            let left = evaluate_expression(&infix.left, env);
+            if left.is_abrupt() {
+                return left;
+            }
+            // `&&` and `||` only look at the right side when they have to.
+            match (infix.operator.as_str(), is_truthy(&left)) {
+                ("&&", false) => return Object::Boolean(false),
+                ("||", true) => return Object::Boolean(true),
+                ("&&", true) | ("||", false) => {
+                    let right = evaluate_expression(&infix.right, env);
+                    if right.is_abrupt() {
+                        return right;
+                    }
+                    return Object::Boolean(is_truthy(&right));
+                }
+                _ => {}
+            }
+            let right = evaluate_expression(&infix.right, env);
+            if right.is_abrupt() {
+                return right;
+            }
+            evaluate_infix_expression(&infix.operator, left, right)
+        }
+        Expression::Integer(integer) => match &integer.value {
+            IntegerValue::Small(value) => Object::Integer(*value),
+            IntegerValue::Big(value) => Object::BigInteger(value.clone()),
+        },
+        Expression::Interpolation(interpolation) => {
+            let mut result = String::new();
+            for part in &interpolation.parts {
+                let value = evaluate_expression(part, env);
+                if value.is_abrupt() {
+                    return value;
+                }
+                result.push_str(&value.inspect());
+            }
+            Object::String(result)
+        }
+        Expression::Map(map) => evaluate_map_literal(map, env),
+        Expression::Prefix(prefix) => {
+            let right = evaluate_expression(&prefix.right, env);
+            if right.is_abrupt() {
+                return right;
+            }
+            evaluate_prefix_expression(&prefix.operator, right)
+        }
+        Expression::Slice(slice) => evaluate_slice_expression(slice, env),
+        Expression::StringLiteral(string) => Object::String(string.value.clone()),
+    }
+}
+
+fn evaluate_block(block: &Block, env: &Env) -> Object {
+    let mut result = Object::Null;
+
+    for statement in &block.statements {
+        result = evaluate_statement(statement, env);
+
+        if let Object::Break | Object::Continue | Object::Return(_) | Object::Error(_) = result {
+            return result;
+        }
+    }
+
+    return result;
+}
+
+/// Like the branches of an `if`, each pass of the body gets its own scope,
+/// so bindings declared inside it are gone after the loop.
+fn evaluate_while_statement(statement: &WhileStatement, env: &Env) -> Object {
+    loop {
+        let condition = evaluate_expression(&statement.condition, env);
+        if condition.is_abrupt() {
+            return condition;
+        }
+        if !is_truthy(&condition) {
+            return Object::Null;
+        }
+
+        match evaluate_block(&statement.body, &Environment::new_enclosed(env)) {
+            Object::Break => return Object::Null,
+            result @ (Object::Return(_) | Object::Error(_)) => return result,
+            _ => {}
+        }
+    }
+}
+
+/// Each iteration gets its own scope holding the loop variable, so the
+/// variable is gone after the loop and closures capture the current item.
+fn evaluate_for_statement(statement: &ForStatement, env: &Env) -> Object {
+    let iterable = evaluate_expression(&statement.iterable, env);
+    if iterable.is_abrupt() {
+        return iterable;
+    }
+
+    let items = match iterate(iterable) {
+        Ok(items) => items,
+        Err(error) => return error,
+    };
+
+    for item in items {
+        let loop_env = Environment::new_enclosed(env);
+        loop_env
+            .borrow_mut()
+            .set(statement.variable.value.clone(), item);
+
+        match evaluate_block(&statement.body, &loop_env) {
+            Object::Break => break,
+            result @ (Object::Return(_) | Object::Error(_)) => return result,
+            _ => {}
+        }
+    }
+
+    return Object::Null;
+}
+
+/// The items a `for` loop visits: the elements of an array, the characters
+/// of a string, the keys of a map or the integers in a range.
+fn iterate(iterable: Object) -> Result<Box<dyn Iterator<Item = Object>>, Object> {
+    return match iterable {
+        Object::Array(elements) => Ok(Box::new(
+            (0..elements.len()).map(move |i| elements[i].clone()),
+        )),
+        Object::Map(pairs) => {
+            let keys: Vec<Object> = pairs.keys().map(|key| key.to_object()).collect();
+            Ok(Box::new(keys.into_iter()))
+        }
+        Object::Range { start, end } => Ok(Box::new((start..end).map(Object::Integer))),
+        Object::String(value) => {
+            let characters: Vec<Object> = value
+                .chars()
+                .map(|c| Object::String(c.to_string()))
+                .collect();
+            Ok(Box::new(characters.into_iter()))
+        }
+        other => Err(Object::Error(format!(
+            "cannot iterate over {:?}",
+            other.object_type()
+        ))),
+    };
+}
+
+fn evaluate_let_statement(statement: &LetStatement, env: &Env) -> Object {
+    let value = evaluate_expression(&statement.value, env);
+    if value.is_abrupt() {
+        return value;
+    }
+
+    env.borrow_mut().set(statement.name.value.clone(), value);
+    return Object::Null;
+}
+
+fn evaluate_map_literal(map: &MapLiteral, env: &Env) -> Object {
+    let mut pairs = BTreeMap::new();
+
+    for (key, value) in &map.pairs {
+        let key = evaluate_expression(key, env);
+        if key.is_abrupt() {
+            return key;
+        }
+        let key = match HashKey::from_object(&key) {
+            Ok(key) => key,
+            Err(error) => return error,
+        };
+
+        let value = evaluate_expression(value, env);
+        if value.is_abrupt() {
+            return value;
+        }
+        pairs.insert(key, value);
+    }
+
+    return Object::map(pairs);
+}
+
+/// A resolved assignment target. Keys are evaluated once, when the target
+/// is resolved, so `a[f()] += 1` calls `f` a single time.
+enum Place {
+    Variable(String),
+    Index(Box<Place>, Object),
+    Field(Box<Place>, String),
+}
+
+fn evaluate_assign_expression(assign: &Assign, env: &Env) -> Object {
+    let place = match resolve_place(&assign.target, env) {
+        Ok(place) => place,
+        Err(error) => return error,
+    };
+
+    // `x += y` is `x = x + y`: the target is read before `y` is evaluated.
+    let current = match assign.operator() {
+        Some(_) => {
+            let current = read_place(&place, env);
+            if current.is_error() {
+                return current;
+            }
+            Some(current)
+        }
+        None => None,
+    };
+
+    let mut value = evaluate_expression(&assign.value, env);
+    if value.is_abrupt() {
+        return value;
+    }
+
+    if let (Some(operator), Some(current)) = (assign.operator(), current) {
+        value = evaluate_infix_expression(operator, current, value);
+        if value.is_error() {
+            return value;
+        }
+    }
+
+    let result = write_place(&place, value.clone(), env);
+    if result.is_error() {
+        return result;
+    }
+    return value;
+}
+
+fn resolve_place(target: &Expression, env: &Env) -> Result<Place, Object> {
+    match target {
+        Expression::Identifier(identifier) => Ok(Place::Variable(identifier.value.clone())),
+        Expression::Index(index) => {
+            let container = resolve_place(&index.left, env)?;
+            let key = evaluate_expression(&index.index, env);
+            if key.is_abrupt() {
+                return Err(key);
+            }
+            Ok(Place::Index(Box::new(container), key))
+        }
+        Expression::Field(field) => {
+            let container = resolve_place(&field.left, env)?;
+            Ok(Place::Field(Box::new(container), field.field.value.clone()))
+        }
+        _ => Err(Object::Error("invalid assignment target".to_owned())),
+    }
+}
+
+fn read_place(place: &Place, env: &Env) -> Object {
+    match place {
+        Place::Variable(name) => match env.borrow().get(name) {
+            Some(value) => value,
+            None => undeclared_identifier(name),
+        },
+        Place::Index(container, key) => {
+            let container = read_place(container, env);
+            if container.is_error() {
+                return container;
+            }
+            evaluate_index_expression(container, key.clone())
+        }
+        Place::Field(container, name) => {
+            let container = read_place(container, env);
+            if container.is_error() {
+                return container;
+            }
+            evaluate_field_expression(container, name)
+        }
+    }
+}
+
+/// Writes `value` to `place`, changing the binding in place.
+fn write_place(place: &Place, value: Object, env: &Env) -> Object {
+    let mut value = Some(value);
+    return update_place(place, true, env, &mut |slot| {
+        *slot = value.take().unwrap();
+        Object::Null
+    });
+}
+
+/// Runs `update` on the value stored at `place` without copying it out of
+/// its binding. Arrays and maps are copied on the way down only when some
+/// other value still shares them, so `a[i] = v` leaves every other copy of
+/// `a` unchanged but does not copy `a` when nothing else holds it. With
+/// `insert`, a missing map key is added rather than being an error.
+fn update_place(
+    place: &Place,
+    insert: bool,
+    env: &Env,
+    update: &mut dyn FnMut(&mut Object) -> Object,
+) -> Object {
+    match place {
+        Place::Variable(name) => match env.borrow_mut().update(name, |binding| update(binding)) {
+            Some(result) => result,
+            None => undeclared_identifier(name),
+        },
+        Place::Index(container, key) => {
+            update_place(container, false, env, &mut |container| match element_mut(
+                container, key, insert,
+            ) {
+                Ok(element) => update(element),
+                Err(error) => error,
+            })
+        }
+        Place::Field(container, name) => update_place(container, false, env, &mut |container| {
+            if !matches!(container, Object::Map(_)) {
+                return Object::Error(format!(
+                    "field assignment not supported: {:?}",
+                    container.object_type()
+                ));
+            }
+            match element_mut(container, &Object::String(name.clone()), insert) {
+                Ok(element) => update(element),
+                Err(error) => error,
+            }
+        }),
+    }
+}
+
+fn undeclared_identifier(name: &str) -> Object {
+    return Object::Error(format!("cannot assign to undeclared identifier: {}", name));
+}
+
+fn evaluate_if_expression(if_expression: &If, env: &Env) -> Object {
+    let condition = evaluate_expression(&if_expression.condition, env);
+    if condition.is_abrupt() {
+        return condition;
+    }
+
+    if is_truthy(&condition) {
+        return evaluate_block(&if_expression.consequence, &Environment::new_enclosed(env));
+    }
+
+    return match &if_expression.alternative {
+        Some(alternative) => evaluate_block(alternative, &Environment::new_enclosed(env)),
+        None => Object::Null,
+    };
+}
+
+fn evaluate_call(call: &Call, env: &Env) -> Object {
+    let function = evaluate_expression(&call.function, env);
+    if function.is_abrupt() {
+        return function;
+    }
+
+    return match evaluate_expressions(&call.arguments, env) {
+        Ok(arguments) => apply_function(function, arguments),
+        Err(error) => error,
+    };
+}
+
+/// Evaluates each expression in order, stopping at the first error or
+/// `return`.
+fn evaluate_expressions(expressions: &[Expression], env: &Env) -> Result<Vec<Object>, Object> {
+    let mut objects = vec![];
+    for expression in expressions {
+        let object = evaluate_expression(expression, env);
+        if object.is_abrupt() {
+            return Err(object);
+        }
+        objects.push(object);
+    }
+
+    return Ok(objects);
+}
+
+fn apply_function(function: Object, arguments: Vec<Object>) -> Object {
+    let (parameters, body, env) = match function {
+        Object::Function {
+            parameters,
+            body,
+            env,
+        } => (parameters, body, env),
+        Object::Builtin { function, .. } => return function(arguments),
+        other => return Object::Error(format!("not a function: {:?}", other.object_type())),
+    };
+
+    if parameters.len() != arguments.len() {
+        return Object::Error(format!(
+            "wrong number of arguments: expected {}, got {}",
+            parameters.len(),
+            arguments.len()
+        ));
+    }
+
+    let function_env = Environment::new_enclosed(&env);
+    for (parameter, argument) in parameters.iter().zip(arguments) {
+        function_env
+            .borrow_mut()
+            .set(parameter.value.clone(), argument);
+    }
+
+    let depth = CALL_DEPTH.with(|depth| depth.get());
+    if depth >= MAX_CALL_DEPTH {
+        return Object::Error("maximum call depth exceeded".to_owned());
+    }
+
+    CALL_DEPTH.with(|call_depth| call_depth.set(depth + 1));
+    let result = evaluate_block(&body, &function_env);
+    CALL_DEPTH.with(|call_depth| call_depth.set(depth));
+
+    return match result {
+        Object::Return(value) => *value,
+        result => result,
+    };
+}
+
+fn evaluate_prefix_expression(operator: &str, right: Object) -> Object {
+    match operator {
+        "!" => Object::Boolean(!is_truthy(&right)),
+        "-" => match right {
+            Object::Float(value) => Object::Float(-value),
+            Object::Integer(value) => match value.checked_neg() {
+                Some(value) => Object::Integer(value),
+                None => Object::from_big_int(-BigInt::from(value)),
+            },
+            Object::BigInteger(value) => Object::from_big_int(-value),
+            other => Object::Error(format!("unknown operator: -{:?}", other.object_type())),
+        },
+        _ => Object::Error(format!(
+            "unknown operator: {}{:?}",
+            operator,
+            right.object_type()
+        )),
+    }
+}
+
+/// Integers and floats mix freely: when one side is a float, the integer is
+/// converted to a float and the result is a float.
+fn evaluate_infix_expression(operator: &str, left: Object, right: Object) -> Object {
+    match (&left, &right) {
+        _ if operator == ".." => evaluate_range_expression(&left, &right),
+        (Object::Integer(left), Object::Integer(right)) => {
+            evaluate_integer_infix_expression(operator, *left, *right)
+        }
+        (
+            Object::Integer(_) | Object::BigInteger(_),
+            Object::Integer(_) | Object::BigInteger(_),
+        ) => evaluate_big_integer_infix_expression(operator, as_big_int(&left), as_big_int(&right)),
+        (
+            Object::Integer(_) | Object::BigInteger(_) | Object::Float(_),
+            Object::Integer(_) | Object::BigInteger(_) | Object::Float(_),
+        ) => evaluate_float_infix_expression(operator, as_float(&left), as_float(&right)),
+        (Object::String(left), Object::String(right)) => {
+            evaluate_string_infix_expression(operator, left, right)
+        }
+        (Object::Boolean(left), Object::Boolean(right)) => match operator {
+            "==" => Object::Boolean(left == right),
+            "!=" => Object::Boolean(left != right),
+            _ => Object::Error(format!("unknown operator: BOOLEAN {} BOOLEAN", operator)),
+        },
+        _ if left.object_type() != right.object_type() => Object::Error(format!(
+            "type mismatch: {:?} {} {:?}",
+            left.object_type(),
+            operator,
+            right.object_type()
+        )),
+        _ => Object::Error(format!(
+            "unknown operator: {:?} {} {:?}",
+            left.object_type(),
+            operator,
+            right.object_type()
+        )),
+    }
+}
+
+fn evaluate_range_expression(left: &Object, right: &Object) -> Object {
+    match (left, right) {
+        (Object::Integer(start), Object::Integer(end)) => Object::Range {
+            start: *start,
+            end: *end,
+        },
+        (Object::BigInteger(bound), _) | (_, Object::BigInteger(bound)) => {
+            Object::Error(format!("range bound too large: {}", bound))
+        }
+        _ => Object::Error(format!(
+            "range bounds must be INTEGERs, got {:?}..{:?}",
+            left.object_type(),
+            right.object_type()
+        )),
+    }
+}
+
+fn evaluate_integer_infix_expression(operator: &str, left: isize, right: isize) -> Object {
+    let result = match operator {
+        "+" => left.checked_add(right),
+        "-" => left.checked_sub(right),
+        "*" => left.checked_mul(right),
+        "/" | "%" if right == 0 => return Object::Error("division by zero".to_owned()),
+        "/" => left.checked_div(right),
+        "%" => left.checked_rem(right),
+        "<" => return Object::Boolean(left < right),
+        ">" => return Object::Boolean(left > right),
+        "<=" => return Object::Boolean(left <= right),
+        ">=" => return Object::Boolean(left >= right),
+        "==" => return Object::Boolean(left == right),
+        "!=" => return Object::Boolean(left != right),
+        _ => return Object::Error(format!("unknown operator: INTEGER {} INTEGER", operator)),
+    };
+
+    return match result {
+        Some(value) => Object::Integer(value),
+        // The result does not fit in an isize, so redo it with big integers.
+        None => {
+            evaluate_big_integer_infix_expression(operator, BigInt::from(left), BigInt::from(right))
+        }
+    };
+}
+
+fn evaluate_big_integer_infix_expression(operator: &str, left: BigInt, right: BigInt) -> Object {
+    match operator {
+        "+" => Object::from_big_int(left + right),
+        "-" => Object::from_big_int(left - right),
+        "*" => Object::from_big_int(left * right),
+        "/" | "%" if right.is_zero() => Object::Error("division by zero".to_owned()),
+        "/" => Object::from_big_int(left / right),
+        "%" => Object::from_big_int(left % right),
+        "<" => Object::Boolean(left < right),
+        ">" => Object::Boolean(left > right),
+        "<=" => Object::Boolean(left <= right),
+        ">=" => Object::Boolean(left >= right),
+        "==" => Object::Boolean(left == right),
+        "!=" => Object::Boolean(left != right),
+        _ => Object::Error(format!("unknown operator: INTEGER {} INTEGER", operator)),
+    }
+}
+
+/// Follows IEEE 754, so dividing by zero gives an infinity or NaN rather
+/// than an error.
+fn evaluate_float_infix_expression(operator: &str, left: f64, right: f64) -> Object {
+    match operator {
+        "+" => Object::Float(left + right),
+        "-" => Object::Float(left - right),
+        "*" => Object::Float(left * right),
+        "/" => Object::Float(left / right),
+        "%" => Object::Float(left % right),
+        "<" => Object::Boolean(left < right),
+        ">" => Object::Boolean(left > right),
+        "<=" => Object::Boolean(left <= right),
+        ">=" => Object::Boolean(left >= right),
+        "==" => Object::Boolean(left == right),
+        "!=" => Object::Boolean(left != right),
+        _ => Object::Error(format!("unknown operator: FLOAT {} FLOAT", operator)),
+    }
+}
+
+fn evaluate_string_infix_expression(operator: &str, left: &str, right: &str) -> Object {
+    match operator {
+        "+" => Object::String(format!("{}{}", left, right)),
+        "==" => Object::Boolean(left == right),
+        "!=" => Object::Boolean(left != right),
+        _ => Object::Error(format!("unknown operator: STRING {} STRING", operator)),
+    }
+}
+
+/// Maps are indexed by key; arrays and strings by position, where a
+/// negative index counts from the end.
+fn evaluate_index_expression(left: Object, index: Object) -> Object {
+    if let Object::Map(pairs) = &left {
+        let key = match HashKey::from_object(&index) {
+            Ok(key) => key,
+            Err(error) => return error,
+        };
+        return match pairs.get(&key) {
+            Some(value) => value.clone(),
+            None => Object::Error(format!("key not found: {}", index.inspect())),
+        };
+    }
+
+    let position = match element_position(&left, &index) {
+        Ok(position) => position,
+        Err(error) => return error,
+    };
+    return match left {
+        Object::Array(elements) => elements[position].clone(),
+        Object::String(value) => Object::String(value.chars().nth(position).unwrap().to_string()),
+        _ => unreachable!("only arrays and strings have a length"),
+    };
+}
+
+/// `map.name` is shorthand for `map["name"]`.
+fn evaluate_field_expression(left: Object, name: &str) -> Object {
+    match left {
+        Object::Map(_) => evaluate_index_expression(left, Object::String(name.to_owned())),
+        other => Object::Error(format!(
+            "field access not supported: {:?}",
+            other.object_type()
+        )),
+    }
+}
+
+/// The element of `container` at `index`, to be assigned to.
+fn element_mut<'a>(
+    container: &'a mut Object,
+    index: &Object,
+    insert: bool,
+) -> Result<&'a mut Object, Object> {
+    match container {
+        Object::Map(pairs) => {
+            let key = HashKey::from_object(index)?;
+            let pairs = Rc::make_mut(pairs);
+            if insert {
+                return Ok(pairs.entry(key).or_insert(Object::Null));
+            }
+            pairs
+                .get_mut(&key)
+                .ok_or_else(|| Object::Error(format!("key not found: {}", index.inspect())))
+        }
+        Object::Array(elements) => {
+            let position = index_position(index, elements.len())?;
+            Ok(&mut Rc::make_mut(elements)[position])
+        }
+        other => Err(Object::Error(format!(
+            "index assignment not supported: {:?}",
+            other.object_type()
+        ))),
+    }
+}
+
+/// Resolves `index` to a position inside the array or string `sequence`.
+fn element_position(sequence: &Object, index: &Object) -> Result<usize, Object> {
+    let length = match sequence_length(sequence) {
+        Some(length) => length,
+        None => {
+            return Err(Object::Error(format!(
+                "index operator not supported: {:?}",
+                sequence.object_type()
+            )))
+        }
+    };
+
+    return index_position(index, length);
+}
+
+/// Resolves `index` to a position in a sequence of `length` elements.
+fn index_position(index: &Object, length: usize) -> Result<usize, Object> {
+    let position = match index {
+        Object::Integer(position) => resolve_position(*position, length).filter(|p| *p < length),
+        Object::BigInteger(_) => None,
+        other => {
+            return Err(Object::Error(format!(
+                "index must be an INTEGER, got {:?}",
+                other.object_type()
+            )))
+        }
+    };
+
+    return position.ok_or_else(|| {
+        Object::Error(format!(
+            "index out of range: {} for length {}",
+            index.inspect(),
+            length
+        ))
+    });
+}
+
+fn evaluate_slice_expression(slice: &Slice, env: &Env) -> Object {
+    let left = evaluate_expression(&slice.left, env);
+    if left.is_abrupt() {
+        return left;
+    }
+
+    let length = match sequence_length(&left) {
+        Some(length) => length,
+        None => {
+            return Object::Error(format!(
+                "slice operator not supported: {:?}",
+                left.object_type()
+            ))
+        }
+    };
+
+    let start = match evaluate_slice_bound(&slice.start, 0, length, env) {
+        Ok(start) => start,
+        Err(error) => return error,
+    };
+    let end = match evaluate_slice_bound(&slice.end, length, length, env) {
+        Ok(end) => end,
+        Err(error) => return error,
+    };
+    if start > end {
+        return Object::Error(format!("slice start {} is after its end {}", start, end));
+    }
+
+    return match left {
+        Object::Array(elements) => Object::array(elements[start..end].to_vec()),
+        Object::String(value) => {
+            Object::String(value.chars().skip(start).take(end - start).collect())
+        }
+        _ => unreachable!("only arrays and strings have a length"),
+    };
+}
+
+/// Evaluates one bound of a slice, using `default` when it is left out.
+fn evaluate_slice_bound(
+    bound: &Option<Box<Expression>>,
+    default: usize,
+    length: usize,
+    env: &Env,
+) -> Result<usize, Object> {
+    let bound = match bound {
+        Some(expression) => evaluate_expression(expression, env),
+        None => return Ok(default),
+    };
+
+    let position = match &bound {
+        _ if bound.is_abrupt() => return Err(bound),
+        Object::Integer(position) => resolve_position(*position, length).filter(|p| *p <= length),
+        Object::BigInteger(_) => None,
+        other => {
+            return Err(Object::Error(format!(
+                "slice bounds must be INTEGERs, got {:?}",
+                other.object_type()
+            )))
+        }
+    };
+
+    return position.ok_or_else(|| {
+        Object::Error(format!(
+            "slice bound out of range: {} for length {}",
+            bound.inspect(),
+            length
+        ))
+    });
+}
+
+fn sequence_length(object: &Object) -> Option<usize> {
+    return match object {
+        Object::Array(elements) => Some(elements.len()),
+        Object::String(value) => Some(value.chars().count()),
+        _ => None,
+    };
+}
+
+/// Turns a possibly negative position into an offset from the start, or
+/// `None` when it points before the start.
+fn resolve_position(position: isize, length: usize) -> Option<usize> {
+    if position >= 0 {
+        return Some(position as usize);
+    }
+    return length.checked_sub(position.unsigned_abs());
+}
+
+fn as_big_int(object: &Object) -> BigInt {
+    match object {
+        Object::Integer(value) => BigInt::from(*value),
+        Object::BigInteger(value) => value.clone(),
+        other => unreachable!("{:?} is not an integer", other.object_type()),
+    }
+}
+
+fn as_float(object: &Object) -> f64 {
+    match object {
+        Object::Float(value) => *value,
+        Object::Integer(value) => *value as f64,
+        Object::BigInteger(value) => value.to_f64().unwrap_or(f64::NAN),
+        other => unreachable!("{:?} is not a number", other.object_type()),
+    }
+}
+
+fn is_truthy(object: &Object) -> bool {
+    match object {
+        Object::Null => false,
+        Object::Boolean(value) => *value,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser};
+
+    fn run(source: &str) -> String {
+        let lexer = Lexer::new(source.to_owned());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program();
+        assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+
+        let env = Environment::new();
+        return evaluate(&program, &env).inspect();
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let tests = [
+            ("!true;", "false"),
+            ("!false;", "true"),
+            ("!5;", "false"),
+            ("!!5;", "true"),
+            ("!\"\";", "false"),
+            ("!if false { 1 };", "true"),
+            ("-5;", "-5"),
+            ("--5;", "5"),
+            ("-2.5;", "-2.5"),
+            ("-true;", "Error: unknown operator: -BOOLEAN"),
+            ("-\"a\";", "Error: unknown operator: -STRING"),
+            ("if true { 10 }", "10"),
+            ("if false { 10 }", "null"),
+            ("if 1 { 10 }", "10"),
+            ("if 0 { 10 } else { 20 }", "10"),
+            ("if \"\" { 10 } else { 20 }", "10"),
+            ("if 1 > 2 { 10 } else { 20 }", "20"),
+            ("if if false { 1 } { 10 } else { 20 }", "20"),
+            ("9; return 2 * 5; 9;", "10"),
+            ("if true { if true { return 10; } return 1; }", "10"),
+            (
+                "declare f = proc() { if true { if true { return 1; } } 2 }; f() + 1;",
+                "2",
+            ),
+            ("declare f = proc(x) { x }; f(1); 3;", "3"),
+            (
+                "declare f = proc(x) { declare y = if x { return 1; } else { 2 }; y + 10 }; f(true);",
+                "1",
+            ),
+            (
+                "declare f = proc(x) { declare y = if x { return 1; } else { 2 }; y + 10 }; f(false);",
+                "12",
+            ),
+            (
+                "declare f = proc() { [1, if true { return 2; }, 3] }; f();",
+                "2",
+            ),
+            (
+                "declare f = proc() { 1 + if true { return 3; } }; f();",
+                "3",
+            ),
+            ("declare f = proc() { len(if true { return 4; }) }; f();", "4"),
+            ("5();", "Error: not a function: INTEGER"),
+            ("\"f\"(1);", "Error: not a function: STRING"),
+            (
+                "declare f = proc(a, b) { a }; f(1);",
+                "Error: wrong number of arguments: expected 2, got 1",
+            ),
+            (
+                "proc() { 1 }(2);",
+                "Error: wrong number of arguments: expected 0, got 1",
+            ),
+            ("5 + true;", "Error: type mismatch: INTEGER + BOOLEAN"),
+            ("5 + true; 5;", "Error: type mismatch: INTEGER + BOOLEAN"),
+            (
+                "true + false;",
+                "Error: unknown operator: BOOLEAN + BOOLEAN",
+            ),
+            ("\"a\" - \"b\";", "Error: unknown operator: STRING - STRING"),
+            (
+                "if 10 > 1 { true + false; 1 }",
+                "Error: unknown operator: BOOLEAN + BOOLEAN",
+            ),
+            (
+                "declare f = proc() { return true + 1; }; f();",
+                "Error: type mismatch: BOOLEAN + INTEGER",
+            ),
+            ("foobar;", "Error: identifier not found: foobar"),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(run(source), expected, "source: {}", source);
+        }
+    }
+
+    #[test]
+    fn test_call_depth_is_limited() {
+        let count = "declare count = proc(n) { if n == 0 { 0 } else { 1 + count(n - 1) } };";
+        let (deepest, too_deep) = std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || {
+                let deepest = run(&format!("{} count({});", count, MAX_CALL_DEPTH - 1));
+                let too_deep = run(&format!("{} count({});", count, MAX_CALL_DEPTH));
+                (deepest, too_deep)
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert_eq!(deepest, (MAX_CALL_DEPTH - 1).to_string());
+        assert_eq!(too_deep, "Error: maximum call depth exceeded");
+        assert_eq!(run("declare f = proc() { 1 }; f();"), "1");
+    }
+
+    #[test]
+    fn test_adder_closure() {
+        let result = run("declare new_adder = proc(x) { proc(y) { x + y } };\
+            declare add_two = new_adder(2);\
+            add_two(3);");
+
+        assert_eq!(result, "5");
+    }
+
+    #[test]
+    fn test_counter_closures_keep_their_own_environment() {
+        let result = run(
+            "declare new_counter = proc(start) { proc(step) { start + step } };\
+            declare from_ten = new_counter(10);\
+            declare from_zero = new_counter(0);\
+            from_ten(1) * 100 + from_zero(1);",
+        );
+
+        assert_eq!(result, "1101");
+    }
+
+    #[test]
+    fn test_recursive_proc() {
+        let result = run("declare factorial = proc(n) {\
+                if (n < 2) { 1 } else { n * factorial(n - 1) }\
+            };\
+            factorial(5);");
+
+        assert_eq!(result, "120");
+    }
+
+    #[test]
+    fn test_declarations_do_not_leak_out_of_procs() {
+        let result = run("declare f = proc() { declare inner = 1; inner }; f(); inner;");
+
+        assert_eq!(result, "Error: identifier not found: inner");
+    }
+
+    #[test]
+    fn test_comparison_operators() {
+        assert_eq!(run("3 <= 3;"), "true");
+        assert_eq!(run("3 <= 2;"), "false");
+        assert_eq!(run("3 >= 4;"), "false");
+        assert_eq!(run("4 >= 4;"), "true");
+    }
+
+    #[test]
+    fn test_mixed_integer_and_float_arithmetic() {
+        let tests = [
+            ("1 + 2.5;", "3.5"),
+            ("-1.5 * 2;", "-3.0"),
+            ("7 / 2;", "3"),
+            ("7 / 2.0;", "3.5"),
+            ("1 == 1.0;", "true"),
+            ("2 >= 2.5;", "false"),
+            ("0.1 + 0.2 > 0.3;", "true"),
+            ("1.0 / 0;", "inf"),
+            ("1 / 0;", "Error: division by zero"),
+            ("1e3 - 0x10;", "984.0"),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(run(source), expected, "source: {}", source);
+        }
+    }
+
+    #[test]
+    fn test_integers_promote_to_big_integers() {
+        let tests = [
+            ("9223372036854775807 + 1;", "9223372036854775808"),
+            ("-9223372036854775807 - 2;", "-9223372036854775809"),
+            ("-(-9223372036854775807 - 1);", "9223372036854775808"),
+            ("(-9223372036854775807 - 1) / -1;", "9223372036854775808"),
+            (
+                "99999999999999999999 * 99999999999999999999;",
+                "9999999999999999999800000000000000000001",
+            ),
+            ("100000000000000000000 / 3;", "33333333333333333333"),
+            ("100000000000000000000 % 7;", "2"),
+            ("100000000000000000000 - 99999999999999999999;", "1"),
+            ("100000000000000000000 > 9223372036854775807;", "true"),
+            (
+                "100000000000000000000 == 10 * 10000000000000000000;",
+                "true",
+            ),
+            ("100000000000000000000 / 0;", "Error: division by zero"),
+            ("100000000000000000000 * 0.5;", "5e19"),
+            ("-7 % 3;", "-1"),
+            ("7.5 % 2;", "1.5"),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(run(source), expected, "source: {}", source);
+        }
+    }
+
+    #[test]
+    fn test_factorial_is_exact() {
+        let result = run("declare factorial = proc(n) {\
+                if (n < 2) { 1 } else { n * factorial(n - 1) }\
+            };\
+            factorial(30);");
+
+        assert_eq!(result, "265252859812191058636308480000000");
+    }
+
+    #[test]
+    fn test_array_indexing() {
+        let tests = [
+            ("[1, 2 * 2, 3 + 3];", "[1, 4, 6]"),
+            ("[1, 2, 3][0];", "1"),
+            ("declare i = 0; [1][i];", "1"),
+            ("declare a = [1, 2, 3]; a[1] + a[2];", "5"),
+            ("[1, 2, 3][-1];", "3"),
+            ("[1, 2, 3][-3];", "1"),
+            ("\"héllo\"[1];", "é"),
+            ("[[1, 2], [3]][0][1];", "2"),
+            ("[1, 2, 3][3];", "Error: index out of range: 3 for length 3"),
+            (
+                "[1, 2, 3][-4];",
+                "Error: index out of range: -4 for length 3",
+            ),
+            ("[][0];", "Error: index out of range: 0 for length 0"),
+            (
+                "[1][100000000000000000000];",
+                "Error: index out of range: 100000000000000000000 for length 1",
+            ),
+            ("[1][true];", "Error: index must be an INTEGER, got BOOLEAN"),
+            ("1[0];", "Error: index operator not supported: INTEGER"),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(run(source), expected, "source: {}", source);
+        }
+    }
+
+    #[test]
+    fn test_slicing() {
+        let tests = [
+            ("[1, 2, 3, 4][1:3];", "[2, 3]"),
+            ("[1, 2, 3, 4][:2];", "[1, 2]"),
+            ("[1, 2, 3, 4][2:];", "[3, 4]"),
+            ("[1, 2, 3, 4][:-1];", "[1, 2, 3]"),
+            ("[1, 2, 3, 4][:];", "[1, 2, 3, 4]"),
+            ("[1, 2][2:];", "[]"),
+            ("\"héllo\"[1:4];", "éll"),
+            (
+                "[1, 2][3:];",
+                "Error: slice bound out of range: 3 for length 2",
+            ),
+            (
+                "[1, 2][:-3];",
+                "Error: slice bound out of range: -3 for length 2",
+            ),
+            ("[1, 2, 3][2:1];", "Error: slice start 2 is after its end 1"),
+            (
+                "[1, 2][\"a\":];",
+                "Error: slice bounds must be INTEGERs, got STRING",
+            ),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(run(source), expected, "source: {}", source);
+        }
+    }
+
+    #[test]
+    fn test_array_builtins() {
+        let tests = [
+            ("len([1, 2, 3]);", "3"),
+            ("len(\"héllo\");", "5"),
+            ("len([]);", "0"),
+            ("first([1, 2, 3]);", "1"),
+            ("last([1, 2, 3]);", "3"),
+            ("rest([1, 2, 3]);", "[2, 3]"),
+            ("rest([]);", "[]"),
+            ("push([1, 2], 3);", "[1, 2, 3]"),
+            ("pop([1, 2, 3]);", "[1, 2]"),
+            ("declare a = [1]; push(a, 2); a;", "[1]"),
+            ("len;", "builtin len"),
+            ("first([]);", "Error: first: the array is empty"),
+            ("last([]);", "Error: last: the array is empty"),
+            ("pop([]);", "Error: pop: the array is empty"),
+            (
+                "len(1);",
+                "Error: argument to `len` not supported, got INTEGER",
+            ),
+            (
+                "len([1], [2]);",
+                "Error: wrong number of arguments: expected 1, got 2",
+            ),
+            ("declare len = proc(x) { 0 }; len([1]);", "0"),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(run(source), expected, "source: {}", source);
+        }
+    }
+
+    #[test]
+    fn test_maps() {
+        let tests = [
+            ("{};", "{}"),
+            (
+                "declare k = \"b\"; {k: 1 + 1, \"a\": 1, true: 3, 10: 4};",
+                "{true: 3, 10: 4, a: 1, b: 2}",
+            ),
+            ("{1: \"x\", 1: \"y\"};", "{1: y}"),
+            ("{\"a\": 1}[\"a\"];", "1"),
+            ("{99999999999999999999: 1}[99999999999999999999];", "1"),
+            ("{\"a\": [1, 2]}[\"a\"][-1];", "2"),
+            ("{\"a\": 1}[\"b\"];", "Error: key not found: b"),
+            ("{[1]: 1};", "Error: unusable as map key: ARRAY"),
+            (
+                "{\"a\": 1}[proc() {}];",
+                "Error: unusable as map key: FUNCTION",
+            ),
+            ("{1.5: 1};", "Error: unusable as map key: FLOAT"),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(run(source), expected, "source: {}", source);
+        }
+    }
+
+    #[test]
+    fn test_index_assignment() {
+        let tests = [
+            ("declare m = {}; m[\"a\"] = 1; m;", "{a: 1}"),
+            ("declare m = {\"a\": 1}; m[\"a\"] = 2;", "2"),
+            ("declare a = [1, 2, 3]; a[-1] = 4; a;", "[1, 2, 4]"),
+            (
+                "declare m = {\"a\": [1, 2]}; m[\"a\"][0] = 3; m;",
+                "{a: [3, 2]}",
+            ),
+            ("declare a = [1]; declare b = a; b[0] = 2; a;", "[1]"),
+            (
+                "declare a = [1]; a[0] = a[1] = 2;",
+                "Error: index out of range: 1 for length 1",
+            ),
+            (
+                "declare m = {}; declare set = proc(v) { m[\"k\"] = v; }; set(5); m;",
+                "{k: 5}",
+            ),
+            (
+                "declare a = [1]; a[1] = 2;",
+                "Error: index out of range: 1 for length 1",
+            ),
+            (
+                "declare m = {}; m[[]] = 1;",
+                "Error: unusable as map key: ARRAY",
+            ),
+            (
+                "declare s = \"ab\"; s[0] = \"c\";",
+                "Error: index assignment not supported: STRING",
+            ),
+            (
+                "m[\"a\"] = 1;",
+                "Error: cannot assign to undeclared identifier: m",
+            ),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(run(source), expected, "source: {}", source);
+        }
+    }
+
+    #[test]
+    fn test_map_builtins() {
+        let tests = [
+            ("len({\"a\": 1, \"b\": 2});", "2"),
+            ("keys({\"b\": 2, \"a\": 1});", "[a, b]"),
+            ("values({\"b\": 2, \"a\": 1});", "[1, 2]"),
+            ("has({\"a\": 1}, \"a\");", "true"),
+            ("has({\"a\": 1}, 1);", "false"),
+            ("remove({\"a\": 1, \"b\": 2}, \"a\");", "{b: 2}"),
+            ("remove({\"a\": 1}, \"b\");", "{a: 1}"),
+            ("declare m = {\"a\": 1}; remove(m, \"a\"); m;", "{a: 1}"),
+            ("has({}, []);", "Error: unusable as map key: ARRAY"),
+            (
+                "keys([1]);",
+                "Error: argument to `keys` not supported, got ARRAY",
+            ),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(run(source), expected, "source: {}", source);
+        }
+    }
+
+    #[test]
+    fn test_reassignment() {
+        let tests = [
+            ("declare x = 1; x = 2; x;", "2"),
+            ("declare x = 1; x = x + 1;", "2"),
+            ("declare x = 1; declare y = 2; x = y = 3; x + y;", "6"),
+            (
+                "declare x = 10; x += 5; x -= 3; x *= 2; x /= 4; x;",
+                "6",
+            ),
+            ("declare s = \"a\"; s += \"b\"; s;", "ab"),
+            (
+                "declare x = 9223372036854775807; x += 1; x;",
+                "9223372036854775808",
+            ),
+            ("declare a = [1, 2]; a[0] += 10; a;", "[11, 2]"),
+            (
+                "declare n = 0; declare inc = proc() { n += 1; }; inc(); inc(); n;",
+                "2",
+            ),
+            (
+                "declare counter = proc() { declare c = 0; proc() { c += 1 } }; declare next = counter(); next(); next();",
+                "2",
+            ),
+            (
+                "declare x = 1; declare f = proc(x) { x = 5; }; f(0); x;",
+                "1",
+            ),
+            (
+                "declare i = 0; declare s = 0; while i < 5 { i += 1; s += i; } s;",
+                "15",
+            ),
+            (
+                "declare total = 0; for x in [1, 2, 3] { total += x; } total;",
+                "6",
+            ),
+            (
+                "x = 1;",
+                "Error: cannot assign to undeclared identifier: x",
+            ),
+            (
+                "z += 1;",
+                "Error: cannot assign to undeclared identifier: z",
+            ),
+            ("declare x = 1; x += (x = 10); x;", "11"),
+            (
+                "declare i = [0]; declare a = [0, 0, 0]; declare g = proc() { i[0] = i[0] + 1; i[0] - 1 }; a[g()] += 5; [a, i];",
+                "[[5, 0, 0], [1]]",
+            ),
+            (
+                "declare m = {}; m.n += 1;",
+                "Error: key not found: n",
+            ),
+            ("declare x = 1; x /= 0;", "Error: division by zero"),
+            (
+                "declare x = true; x += 1;",
+                "Error: type mismatch: BOOLEAN + INTEGER",
+            ),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(run(source), expected, "source: {}", source);
+        }
+    }
+
+    #[test]
+    fn test_fields() {
+        let tests = [
+            ("declare m = {\"a\": 1}; m.a;", "1"),
+            ("declare m = {\"a\": 1}; m.b = 2; m;", "{a: 1, b: 2}"),
+            (
+                "declare m = {\"p\": {\"x\": 1}}; m.p.x += 1; m;",
+                "{p: {x: 2}}",
+            ),
+            (
+                "declare m = {}; m.missing;",
+                "Error: key not found: missing",
+            ),
+            ("[1].x;", "Error: field access not supported: ARRAY"),
+            (
+                "declare a = [1]; a.x = 1;",
+                "Error: field assignment not supported: ARRAY",
+            ),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(run(source), expected, "source: {}", source);
+        }
+    }
+
+    #[test]
+    fn test_while_loops() {
+        let tests = [
+            ("declare i = [0]; while i[0] < 5 { i[0] = i[0] + 1; } i[0];", "5"),
+            (
+                "declare x = [0]; while x[0] < 3 { x[0] = x[0] + 1; declare y = x[0]; } y;",
+                "Error: identifier not found: y",
+            ),
+            ("while false { 1 }", "null"),
+            (
+                "declare n = [0]; while true { n[0] = n[0] + 1; if n[0] == 3 { break; } } n[0];",
+                "3",
+            ),
+            (
+                "declare n = [0, 0]; while n[0] < 5 { n[0] = n[0] + 1; if n[0] % 2 == 0 { continue; } n[1] = n[1] + 1; } n[1];",
+                "3",
+            ),
+            (
+                "declare i = 0; while true { i += 1; declare done = if i == 3 { break; } else { false }; } i;",
+                "3",
+            ),
+            (
+                "declare i = 0; declare s = []; while i < 4 { i += 1; s = push(s, if i % 2 == 0 { continue; } else { i }); } s;",
+                "[1, 3]",
+            ),
+            (
+                "while 1 + true { }",
+                "Error: type mismatch: INTEGER + BOOLEAN",
+            ),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(run(source), expected, "source: {}", source);
+        }
+    }
+
+    #[test]
+    fn test_for_loops() {
+        let tests = [
+            (
+                "declare s = [0]; for x in [1, 2, 3] { s[0] = s[0] + x; } s[0];",
+                "6",
+            ),
+            (
+                "declare s = [\"\"]; for c in \"héllo\" { s[0] = c + s[0]; } s[0];",
+                "olléh",
+            ),
+            (
+                "declare s = [[]]; for k in {\"b\": 1, \"a\": 2} { s[0] = push(s[0], k); } s[0];",
+                "[a, b]",
+            ),
+            (
+                "declare s = [0]; for i in 0..10 { if i % 2 == 0 { continue; } s[0] = s[0] + i; } s[0];",
+                "25",
+            ),
+            (
+                "declare s = [0]; for i in 0..3 { for j in 0..3 { if j == 1 { break; } s[0] = s[0] + 1; } } s[0];",
+                "3",
+            ),
+            (
+                "declare find = proc(a, t) { for x in a { if x == t { return true; } } false }; find([1, 2, 3], 2);",
+                "true",
+            ),
+            (
+                "declare fs = [[]]; for i in 0..3 { fs[0] = push(fs[0], proc() { i }); } fs[0][1]();",
+                "1",
+            ),
+            ("for x in 3..1 { x + true; }", "null"),
+            (
+                "declare s = []; for x in [1, 2, 3] { s = push(s, if x == 2 { continue; } else { x }); } s;",
+                "[1, 3]",
+            ),
+            (
+                "declare s = 0; for x in [1, 2, 3] { declare y = if x == 2 { break; } else { x }; s += y; } s;",
+                "1",
+            ),
+            (
+                "declare s = 0; for x in 0..5 { s += x * if x == 3 { continue; } else { 1 }; } s;",
+                "7",
+            ),
+            ("for x in [1] { } x;", "Error: identifier not found: x"),
+            ("for x in 5 { }", "Error: cannot iterate over INTEGER"),
+            (
+                "for x in [1] { x + true; }",
+                "Error: type mismatch: INTEGER + BOOLEAN",
+            ),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(run(source), expected, "source: {}", source);
+        }
+    }
+
+    #[test]
+    fn test_ranges() {
+        let tests = [
+            ("0..3;", "0..3"),
+            ("1 + 1..2 * 3;", "2..6"),
+            ("len(2..5);", "3"),
+            ("len(5..2);", "0"),
+            (
+                "1.5..2;",
+                "Error: range bounds must be INTEGERs, got FLOAT..INTEGER",
+            ),
+            (
+                "0..99999999999999999999;",
+                "Error: range bound too large: 99999999999999999999",
+            ),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(run(source), expected, "source: {}", source);
+        }
+    }
+
+    #[test]
+    fn test_logical_operators_short_circuit() {
+        assert_eq!(run("false && undefined;"), "false");
+        assert_eq!(run("true || undefined;"), "true");
+        assert_eq!(run("true && 1 > 2;"), "false");
+        assert_eq!(run("false || 1 < 2;"), "true");
+        assert_eq!(
+            run("true && undefined;"),
+            "Error: identifier not found: undefined"
+        );
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        let result = run("declare name = \"Ada\"; declare age = 36;\
+            \"hello ${name}, you are ${age + 1} and ${age > 40} ${\"!\"}\";");
+
+        assert_eq!(result, "hello Ada, you are 37 and false !");
+        assert_eq!(
+            run("\"${missing}\";"),
+            "Error: identifier not found: missing"
+        );
+    }
+
+    #[test]
+    fn test_parameters_shadow_outer_bindings() {
+        let result = run("declare x = 1; declare f = proc(x) { x }; f(2) + x;");
+
+        assert_eq!(result, "3");
+    }
+}
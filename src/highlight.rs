@@ -0,0 +1,156 @@
+use colored::{Color, ColoredString, Colorize};
+
+use crate::{
+    lexer::Lexer,
+    object::Object,
+    token::{Token, TokenType},
+};
+
+/// The color a token is highlighted with, or `None` to leave it as typed.
+fn token_color(token_type: &TokenType) -> Option<Color> {
+    match token_type {
+        TokenType::FUNCTION
+        | TokenType::LET
+        | TokenType::IF
+        | TokenType::ELSE
+        | TokenType::RETURN
+        | TokenType::WHILE
+        | TokenType::FOR
+        | TokenType::IN
+        | TokenType::BREAK
+        | TokenType::CONTINUE => Some(Color::Magenta),
+        TokenType::TRUE | TokenType::FALSE | TokenType::INT | TokenType::FLOAT => Some(Color::Cyan),
+        TokenType::STRING
+        | TokenType::STRINGSTART
+        | TokenType::STRINGMIDDLE
+        | TokenType::STRINGEND => Some(Color::Green),
+        TokenType::AND
+        | TokenType::ASSIGN
+        | TokenType::DIVISION
+        | TokenType::DIVISIONASSIGN
+        | TokenType::EQ
+        | TokenType::GT
+        | TokenType::GTEQ
+        | TokenType::LT
+        | TokenType::LTEQ
+        | TokenType::MINUS
+        | TokenType::MINUSASSIGN
+        | TokenType::MODULO
+        | TokenType::MULTIPLICATION
+        | TokenType::MULTIPLICATIONASSIGN
+        | TokenType::NEGATION
+        | TokenType::NOTEQ
+        | TokenType::OR
+        | TokenType::PLUS
+        | TokenType::PLUSASSIGN
+        | TokenType::RANGE => Some(Color::Yellow),
+        _ => None,
+    }
+}
+
+fn style(text: &str, token: &Token) -> ColoredString {
+    match token_color(&token.token_type) {
+        Some(Color::Magenta) => text.magenta().bold(),
+        Some(color) => text.color(color),
+        None => text.normal(),
+    }
+}
+
+/// Colors `source` token by token. Whitespace and anything the lexer could
+/// not make sense of, such as `ILLEGAL` tokens, is copied through untouched.
+pub fn highlight(source: &str) -> String {
+    let mut lexer = Lexer::new(source.to_owned());
+    let mut out = String::new();
+    let mut copied = 0;
+
+    loop {
+        let token = lexer.next_token();
+        if token.token_type == TokenType::EOF {
+            break;
+        }
+
+        let (start, end) = (token.span.start.offset, token.span.end.offset);
+        let (gap, text) = match (source.get(copied..start), source.get(start..end)) {
+            (Some(gap), Some(text)) => (gap, text),
+            _ => break,
+        };
+
+        out.push_str(gap);
+        out.push_str(&style(text, &token).to_string());
+        copied = end;
+    }
+
+    out.push_str(source.get(copied..).unwrap_or(""));
+    return out;
+}
+
+/// `object.inspect()`, colored by the type of the value.
+pub fn colorize(object: &Object) -> String {
+    match object {
+        Object::Array(elements) => {
+            let elements = elements
+                .iter()
+                .map(colorize)
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!("[{}]", elements)
+        }
+        Object::BigInteger(_)
+        | Object::Boolean(_)
+        | Object::Float(_)
+        | Object::Integer(_)
+        | Object::Range { .. } => object.inspect().cyan().to_string(),
+        Object::Error(_) => object.inspect().red().to_string(),
+        Object::Builtin { .. } | Object::Function { .. } => object.inspect().magenta().to_string(),
+        Object::Map(pairs) => {
+            let pairs = pairs
+                .iter()
+                .map(|(key, value)| format!("{}: {}", colorize(&key.to_object()), colorize(value)))
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!("{{{}}}", pairs)
+        }
+        Object::Break | Object::Continue | Object::Null => object.inspect().dimmed().to_string(),
+        Object::Return(value) => colorize(value),
+        Object::String(_) => object.inspect().green().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_colors() {
+        let tests = [
+            (TokenType::FUNCTION, Some(Color::Magenta)),
+            (TokenType::LET, Some(Color::Magenta)),
+            (TokenType::STRING, Some(Color::Green)),
+            (TokenType::INT, Some(Color::Cyan)),
+            (TokenType::PLUS, Some(Color::Yellow)),
+            (TokenType::IDENT, None),
+            (TokenType::ILLEGAL, None),
+        ];
+
+        for (token_type, expected) in tests {
+            assert_eq!(token_color(&token_type), expected, "{:?}", token_type);
+        }
+    }
+
+    #[test]
+    fn test_highlight_preserves_source() {
+        colored::control::set_override(false);
+
+        let tests = [
+            "declare x = 5;",
+            "  proc(a, b)  {  a + b }",
+            "if (x != 10) { \"ten\" } else { @ }",
+            "\"unterminated",
+            "x ? y # z",
+        ];
+
+        for source in tests {
+            assert_eq!(highlight(source), source);
+        }
+    }
+}
@@ -1,175 +1,827 @@
-use crate::token::{lookup_token_type, Token, TokenType};
+use std::collections::HashMap;
 
+use crate::{
+    diagnostic::Diagnostic,
+    token::{keywords, operators, Position, Span, Token, TokenType},
+};
+
+const ESCAPES_NOTE: &str = "supported escapes are \\n, \\t, \\r, \\0, \\\\, \\\", \\$ and \\u{...}";
+
+/// Walks the source with a byte cursor, so reading a character and slicing
+/// out a literal are both O(1) no matter how far into the file we are.
+///
+/// Every `ILLEGAL` token comes with a diagnostic explaining it, which the
+/// parser collects through `take_errors`.
 pub struct Lexer {
     source: String,
-    position: usize,
-    read_position: usize,
-    character: String,
+    character: Option<char>,
+    location: Position,
+    doc_comments: Vec<String>,
+    errors: Vec<Diagnostic>,
+    /// One entry per `${` we are inside of: the opening quote of its string
+    /// and how many `{` have been opened since, so we know which `}` ends it.
+    interpolations: Vec<(Position, usize)>,
+    keywords: HashMap<&'static str, TokenType>,
+    operators: Vec<(&'static str, TokenType)>,
 }
 
 impl Lexer {
     pub fn new(source: String) -> Lexer {
+        let character = source.chars().next();
         return Lexer {
-            character: "".to_owned(),
             source,
-            position: 0,
-            read_position: 0,
+            character,
+            location: Position::default(),
+            doc_comments: vec![],
+            errors: vec![],
+            interpolations: vec![],
+            keywords: keywords(),
+            operators: operators(),
         };
     }
 
     pub fn next_token(&mut self) -> Token {
-        self.skip_whitespace();
+        if let Err(opening) = self.skip_whitespace() {
+            self.errors.push(
+                Diagnostic::error("unterminated block comment".to_owned(), opening)
+                    .with_code("E0004")
+                    .with_primary_label("comment opened here is never closed"),
+            );
+            let mut token = Token::new(TokenType::ILLEGAL, "/*".to_owned());
+            token.span = opening;
+            return token;
+        }
+
+        let start = self.location;
+        let mut token = self.read_token();
+        token.span = Span::new(start, self.location);
+        token.doc_comments = std::mem::take(&mut self.doc_comments);
+
+        return token;
+    }
+
+    /// Hands over the diagnostics reported since the last call.
+    pub fn take_errors(&mut self) -> Vec<Diagnostic> {
+        return std::mem::take(&mut self.errors);
+    }
+
+    fn read_token(&mut self) -> Token {
+        if let Some(token) = self.read_operator() {
+            return token;
+        }
 
-        let mut token;
+        if let Some(hashes) = self.raw_string_hashes() {
+            return self.read_raw_string(hashes);
+        }
+
+        let character = match self.character {
+            Some(character) => character,
+            None => return Token::new(TokenType::EOF, "".to_owned()),
+        };
 
-        match self.character.as_str() {
-            "=" => {
-                if self.peek_character() == "=" {
-                    token = Token::new(TokenType::EQ, "==".to_owned())
+        let token_type = match character {
+            '(' => TokenType::LPAREN,
+            ')' => TokenType::RPAREN,
+            '{' => {
+                if let Some((_, depth)) = self.interpolations.last_mut() {
+                    *depth += 1;
                 }
-                token = Token::new(TokenType::ASSIGN, self.character.clone());
-            }
-            "+" => {
-                token = Token::new(TokenType::PLUS, self.character.clone());
-            }
-            "" => {
-                token = Token::new(TokenType::EOF, self.character.clone());
-            }
-            "(" => {
-                token = Token::new(TokenType::LPAREN, self.character.clone());
-            }
-            ")" => {
-                token = Token::new(TokenType::RPAREN, self.character.clone());
-            }
-            "{" => {
-                token = Token::new(TokenType::LBRACE, self.character.clone());
-            }
-            "}" => {
-                token = Token::new(TokenType::RBRACE, self.character.clone());
-            }
-            "," => {
-                token = Token::new(TokenType::COMMA, self.character.clone());
-            }
-            ";" => {
-                token = Token::new(TokenType::SEMICOLON, self.character.clone());
+                TokenType::LBRACE
             }
-            "-" => {
-                token = Token::new(TokenType::MINUS, self.character.clone());
+            '}' => match self.interpolations.last_mut() {
+                Some(&mut (opening, 0)) => {
+                    self.interpolations.pop();
+                    self.read_char();
+                    return self.read_string(
+                        opening,
+                        TokenType::STRINGEND,
+                        TokenType::STRINGMIDDLE,
+                    );
+                }
+                Some((_, depth)) => {
+                    *depth -= 1;
+                    TokenType::RBRACE
+                }
+                None => TokenType::RBRACE,
+            },
+            '[' => TokenType::LBRACKET,
+            ']' => TokenType::RBRACKET,
+            ':' => TokenType::COLON,
+            ',' => TokenType::COMMA,
+            ';' => TokenType::SEMICOLON,
+            '"' => {
+                let opening = self.location;
+                self.read_char();
+                return self.read_string(opening, TokenType::STRING, TokenType::STRINGSTART);
             }
-            "/" => {
-                token = Token::new(TokenType::DIVISION, self.character.clone());
+            c if is_identifier_start(c) => {
+                let literal = self.read_identifier();
+                let token_type = match self.keywords.get(literal.as_str()) {
+                    Some(token_type) => token_type.clone(),
+                    None => TokenType::IDENT,
+                };
+                return Token::new(token_type, literal);
             }
-            "*" => {
-                token = Token::new(TokenType::MULTIPLICATION, self.character.clone());
+            c if c.is_ascii_digit() => return self.read_number(),
+            _ => {
+                let start = self.location;
+                self.read_char();
+                self.errors.push(
+                    Diagnostic::error(
+                        format!("unknown start of token: {}", character),
+                        Span::new(start, self.location),
+                    )
+                    .with_code("E0008")
+                    .with_primary_label("this character is not part of the language"),
+                );
+                return Token::new(TokenType::ILLEGAL, character.to_string());
             }
-            "<" => {
-                token = Token::new(TokenType::LT, self.character.clone());
+        };
+
+        self.read_char();
+        return Token::new(token_type, character.to_string());
+    }
+
+    /// Reads the longest operator starting at the current character.
+    fn read_operator(&mut self) -> Option<Token> {
+        let rest = self.rest();
+        let (operator, token_type) = self
+            .operators
+            .iter()
+            .filter(|(operator, _)| rest.starts_with(operator))
+            .max_by_key(|(operator, _)| operator.len())
+            .cloned()?;
+
+        for _ in operator.chars() {
+            self.read_char();
+        }
+
+        return Some(Token::new(token_type, operator.to_owned()));
+    }
+
+    /// Reads an integer (`42`, `0xff`, `0o17`, `0b1010`) or float (`3.14`,
+    /// `1e-9`) literal exactly as written, `_` separators included. The
+    /// parser works out its value.
+    fn read_number(&mut self) -> Token {
+        let start = self.location.offset;
+        let radix = match self.rest().get(..2) {
+            Some("0x" | "0X") => 16,
+            Some("0o" | "0O") => 8,
+            Some("0b" | "0B") => 2,
+            _ => 10,
+        };
+
+        let mut token_type = TokenType::INT;
+        if radix != 10 {
+            self.read_char();
+            self.read_char();
+            self.read_digits(radix);
+        } else {
+            self.read_digits(10);
+
+            if self.character == Some('.') && starts_with_digit(&self.rest()[1..]) {
+                token_type = TokenType::FLOAT;
+                self.read_char();
+                self.read_digits(10);
             }
-            ">" => {
-                token = Token::new(TokenType::GT, self.character.clone());
+
+            if matches!(self.character, Some('e' | 'E')) {
+                let rest = &self.rest()[1..];
+                let exponent = rest.strip_prefix(['+', '-']).unwrap_or(rest);
+                if starts_with_digit(exponent) {
+                    token_type = TokenType::FLOAT;
+                    let marker_length = self.rest().len() - exponent.len();
+                    for _ in 0..marker_length {
+                        self.read_char();
+                    }
+                    self.read_digits(10);
+                }
             }
-            "!" => {
-                if self.peek_character() == "=" {
+        }
+
+        let literal = self.source[start..self.location.offset].to_owned();
+        return Token::new(token_type, literal);
+    }
+
+    fn read_digits(&mut self, radix: u32) {
+        while matches!(self.character, Some(c) if c.is_digit(radix) || c == '_') {
+            self.read_char();
+        }
+    }
+
+    fn read_identifier(&mut self) -> String {
+        let start = self.location.offset;
+        while matches!(self.character, Some(c) if is_identifier_continue(c)) {
+            self.read_char();
+        }
+
+        return self.source[start..self.location.offset].to_owned();
+    }
+
+    /// Reads string contents up to the closing `"` or the next `${`,
+    /// resolving escapes. The segment is lexed as `closed` when it reaches
+    /// the quote and as `interpolated` when an expression follows it.
+    fn read_string(
+        &mut self,
+        opening: Position,
+        closed: TokenType,
+        interpolated: TokenType,
+    ) -> Token {
+        let mut value = String::new();
+        loop {
+            match self.character {
+                Some('"') => {
                     self.read_char();
-                    token = Token::new(TokenType::NOTEQ, "!=".to_owned());
-                } else {
-                    token = Token::new(TokenType::NEGATION, self.character.clone());
+                    return Token::new(closed, value);
                 }
-            }
-            // TODO: is letter, is number, read string
-            tkn => {
-                if self.is_letter(self.character.as_str()) {
-                    let literal = self.read_identifier();
-                    return Token::new(lookup_token_type(&literal), literal);
+                Some('$') if self.rest().starts_with("${") => {
+                    self.read_char();
+                    self.read_char();
+                    self.interpolations.push((opening, 0));
+                    return Token::new(interpolated, value);
                 }
-
-                if self.is_number(self.character.as_str()) {
-                    let literal = self.read_number();
-                    return Token::new(TokenType::INT, literal);
+                Some('\\') => self.read_escape(&mut value),
+                Some(c) => {
+                    value.push(c);
+                    self.read_char();
                 }
-
-                if tkn == "\"" {
-                    let literal = self.read_string();
-                    return Token::new(TokenType::STRING, literal);
+                None => {
+                    self.unterminated_string(opening, 1);
+                    return Token::new(TokenType::ILLEGAL, "\"".to_owned());
                 }
+            }
+        }
+    }
+
+    /// Reads the escape sequence at a `\`, pushing the character it stands
+    /// for onto `value`. Invalid escapes are reported and kept as written.
+    fn read_escape(&mut self, value: &mut String) {
+        let start = self.location;
+        self.read_char();
 
-                token = Token::new(TokenType::ILLEGAL, self.character.clone());
+        let escaped = match self.character {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some('\\') => '\\',
+            Some('"') => '"',
+            Some('$') => '$',
+            Some('u') => return self.read_unicode_escape(start, value),
+            // Let `read_string` report the missing closing quote.
+            None => return,
+            Some(other) => {
+                self.read_char();
+                value.push('\\');
+                value.push(other);
+                self.errors.push(
+                    Diagnostic::error(
+                        format!("unknown character escape: \\{}", other),
+                        Span::new(start, self.location),
+                    )
+                    .with_code("E0006")
+                    .with_primary_label("unknown escape")
+                    .with_note(ESCAPES_NOTE),
+                );
+                return;
             }
+        };
+
+        self.read_char();
+        value.push(escaped);
+    }
+
+    /// Reads the `u{...}` part of a `\u{...}` escape, which takes one to six
+    /// hex digits naming a Unicode scalar value.
+    fn read_unicode_escape(&mut self, start: Position, value: &mut String) {
+        self.read_char();
+        if self.character != Some('{') {
+            return self.invalid_unicode_escape(start, "expected `{` after `\\u`");
+        }
+        self.read_char();
+
+        let digits_start = self.location.offset;
+        while matches!(self.character, Some(c) if c.is_ascii_hexdigit()) {
+            self.read_char();
         }
+        let digits = self.source[digits_start..self.location.offset].to_owned();
 
+        if self.character != Some('}') {
+            return self.invalid_unicode_escape(start, "expected `}` after the hex digits");
+        }
         self.read_char();
-        return token;
+
+        if digits.is_empty() || digits.len() > 6 {
+            return self.invalid_unicode_escape(start, "expected 1 to 6 hex digits");
+        }
+        match u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+        {
+            Some(character) => value.push(character),
+            None => self.invalid_unicode_escape(start, "not a Unicode scalar value"),
+        }
     }
 
-    fn is_letter(&self, character: &str) -> bool {
-        return character.chars().all(|c| c.is_alphabetic() || c == '_');
+    fn invalid_unicode_escape(&mut self, start: Position, label: &str) {
+        self.errors.push(
+            Diagnostic::error(
+                "invalid unicode escape".to_owned(),
+                Span::new(start, self.location),
+            )
+            .with_code("E0007")
+            .with_primary_label(label)
+            .with_note(ESCAPES_NOTE),
+        );
     }
 
-    fn is_number(&self, character: &str) -> bool {
-        return character.chars().all(|c| c.is_numeric());
+    /// Returns the number of `#`s when the cursor is at a raw string such as
+    /// `r"..."` or `r#"..."#`.
+    fn raw_string_hashes(&self) -> Option<usize> {
+        let rest = self.rest().strip_prefix('r')?;
+        let hashes = rest.len() - rest.trim_start_matches('#').len();
+        if !rest[hashes..].starts_with('"') {
+            return None;
+        }
+        return Some(hashes);
     }
 
-    fn read_number(&mut self) -> String {
-        let initial_position = self.position;
-        while self.is_number(self.character.as_str()) {
+    /// Reads a raw string, which ends at a `"` followed by as many `#`s as
+    /// it was opened with. Nothing inside it is escaped, so it can span
+    /// several lines and contain quotes.
+    fn read_raw_string(&mut self, hashes: usize) -> Token {
+        let opening = self.location;
+        let delimiter = format!("r{}\"", "#".repeat(hashes));
+        for _ in delimiter.chars() {
             self.read_char();
         }
 
-        return self.source[initial_position..self.position].to_owned();
+        let closing = format!("\"{}", "#".repeat(hashes));
+        let start = self.location.offset;
+        while !self.rest().starts_with(&closing) {
+            if self.character.is_none() {
+                self.unterminated_string(opening, delimiter.len());
+                return Token::new(TokenType::ILLEGAL, delimiter);
+            }
+            self.read_char();
+        }
+
+        let value = self.source[start..self.location.offset].to_owned();
+        for _ in closing.chars() {
+            self.read_char();
+        }
+        return Token::new(TokenType::STRING, value);
     }
 
-    fn read_identifier(&mut self) -> String {
-        let initial_position = self.position;
-        let mut is_first_letter = true;
-        while self.is_letter(self.character.as_str())
-            || (!is_first_letter && self.is_number(self.character.as_str()))
-        {
+    fn unterminated_string(&mut self, opening: Position, delimiter_length: usize) {
+        let mut end = opening;
+        end.offset += delimiter_length;
+        end.column += delimiter_length;
+        self.errors.push(
+            Diagnostic::error("unterminated string".to_owned(), Span::new(opening, end))
+                .with_code("E0005")
+                .with_primary_label("string opened here is never closed"),
+        );
+    }
+
+    /// The source from the current character to the end of the input.
+    fn rest(&self) -> &str {
+        return &self.source[self.location.offset..];
+    }
+
+    fn read_char(&mut self) {
+        let character = match self.character {
+            Some(character) => character,
+            None => return,
+        };
+
+        self.location.offset += character.len_utf8();
+        // A `\r\n` pair needs no special case: the `\r` only moves the
+        // column and the `\n` after it starts the next line.
+        if character == '\n' {
+            self.location.line += 1;
+            self.location.column = 1;
+        } else {
+            self.location.column += 1;
+        }
+
+        self.character = self.rest().chars().next();
+    }
+
+    /// Skips any Unicode whitespace and comments. Fails with the span of the
+    /// opening `/*` when a block comment is never closed.
+    fn skip_whitespace(&mut self) -> Result<(), Span> {
+        loop {
+            match self.character {
+                Some(c) if c.is_whitespace() => self.read_char(),
+                Some('/') if self.rest().starts_with("//") => self.skip_line_comment(),
+                Some('/') if self.rest().starts_with("/*") => self.skip_block_comment()?,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Skips a `//` comment, keeping the text of `///` doc comments so it
+    /// can be attached to the next token.
+    fn skip_line_comment(&mut self) {
+        let start = self.location.offset;
+        while !matches!(self.character, None | Some('\n')) {
             self.read_char();
-            is_first_letter = false;
         }
-        return self.source[initial_position..self.position].to_owned();
+
+        let comment = self.source[start..self.location.offset].trim_end_matches('\r');
+        if let Some(doc) = comment.strip_prefix("///") {
+            if !doc.starts_with('/') {
+                let doc = doc.strip_prefix(' ').unwrap_or(doc).to_owned();
+                self.doc_comments.push(doc);
+            }
+        }
     }
 
-    fn read_string(&mut self) -> String {
-        let mut result = String::new();
-        while self.character != "\"" {
-            result.push_str(&self.character);
+    /// Skips a `/* */` comment, including any block comments nested in it.
+    fn skip_block_comment(&mut self) -> Result<(), Span> {
+        let start = self.location;
+        self.read_char();
+        self.read_char();
+        let opening = Span::new(start, self.location);
+
+        let mut depth = 1;
+        while depth > 0 {
+            if self.character.is_none() {
+                return Err(opening);
+            }
+            if self.rest().starts_with("/*") {
+                depth += 1;
+                self.read_char();
+            } else if self.rest().starts_with("*/") {
+                depth -= 1;
+                self.read_char();
+            }
             self.read_char();
         }
-        return result;
+
+        return Ok(());
     }
+}
+
+/// Identifiers follow Unicode's XID rules, as Rust's do: they start with a
+/// letter or `_` and continue with letters, digits, `_` and combining marks.
+fn is_identifier_start(character: char) -> bool {
+    return unicode_ident::is_xid_start(character) || character == '_';
+}
+
+fn is_identifier_continue(character: char) -> bool {
+    return unicode_ident::is_xid_continue(character);
+}
+
+fn starts_with_digit(text: &str) -> bool {
+    return text.starts_with(|c: char| c.is_ascii_digit());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn peek_character(&self) -> String {
-        if self.read_position >= self.source.len() {
-            return "".to_owned();
+    #[test]
+    fn test_operators_use_longest_match() {
+        let mut lexer = Lexer::new("a<=b>=c==d!=e&&f||!g<h>i=j..k.l+=m-=n*=o/=p & |".to_owned());
+        let mut operators = vec![];
+        loop {
+            let token = lexer.next_token();
+            match token.token_type {
+                TokenType::EOF => break,
+                TokenType::IDENT => {}
+                token_type => operators.push((token_type, token.literal)),
+            }
         }
-        return self
-            .source
-            .chars()
-            .nth(self.read_position)
-            .unwrap()
-            .to_string();
+
+        let expected = [
+            (TokenType::LTEQ, "<="),
+            (TokenType::GTEQ, ">="),
+            (TokenType::EQ, "=="),
+            (TokenType::NOTEQ, "!="),
+            (TokenType::AND, "&&"),
+            (TokenType::OR, "||"),
+            (TokenType::NEGATION, "!"),
+            (TokenType::LT, "<"),
+            (TokenType::GT, ">"),
+            (TokenType::ASSIGN, "="),
+            (TokenType::RANGE, ".."),
+            (TokenType::DOT, "."),
+            (TokenType::PLUSASSIGN, "+="),
+            (TokenType::MINUSASSIGN, "-="),
+            (TokenType::MULTIPLICATIONASSIGN, "*="),
+            (TokenType::DIVISIONASSIGN, "/="),
+            (TokenType::ILLEGAL, "&"),
+            (TokenType::ILLEGAL, "|"),
+        ];
+        let expected: Vec<(TokenType, String)> = expected
+            .iter()
+            .map(|(token_type, literal)| (token_type.clone(), literal.to_string()))
+            .collect();
+        assert_eq!(operators, expected);
     }
 
-    fn read_char(&mut self) {
-        if self.read_position >= self.source.len() {
-            self.character = "".to_owned();
-        } else {
-            self.character = self
-                .source
-                .chars()
-                .nth(self.read_position)
-                .unwrap()
-                .to_string();
+    #[test]
+    fn test_comments_are_skipped() {
+        let mut lexer = Lexer::new(
+            "// line comment\n\
+            a /* block /* nested */ still a comment */ / b // trailing\n\
+            /* open"
+                .to_owned(),
+        );
+
+        let expected = [
+            (TokenType::IDENT, "a"),
+            (TokenType::DIVISION, "/"),
+            (TokenType::IDENT, "b"),
+            (TokenType::ILLEGAL, "/*"),
+            (TokenType::EOF, ""),
+        ];
+        for (token_type, literal) in expected {
+            let token = lexer.next_token();
+            assert_eq!(token.token_type, token_type);
+            assert_eq!(token.literal, literal);
         }
+    }
+
+    #[test]
+    fn test_doc_comments_attach_to_next_token() {
+        let mut lexer = Lexer::new(
+            "/// Adds two numbers.\n\
+            ///\n\
+            //// not a doc comment\n\
+            ///    Keeps indentation.\n\
+            declare add = 1;"
+                .to_owned(),
+        );
 
-        self.position = self.read_position;
-        self.read_position += 1;
+        let token = lexer.next_token();
+        assert_eq!(token.token_type, TokenType::LET);
+        assert_eq!(
+            token.doc_comments,
+            vec!["Adds two numbers.", "", "   Keeps indentation."]
+        );
+        assert!(lexer.next_token().doc_comments.is_empty());
     }
 
-    fn skip_whitespace(&mut self) {
-        while self.character == " " {
-            self.read_char();
+    fn lines_and_columns(source: &str) -> Vec<(TokenType, String, usize, usize)> {
+        let mut lexer = Lexer::new(source.to_owned());
+        let mut tokens = vec![];
+        loop {
+            let token = lexer.next_token();
+            let start = token.span.start;
+            tokens.push((
+                token.token_type.clone(),
+                token.literal,
+                start.line,
+                start.column,
+            ));
+            if token.token_type == TokenType::EOF {
+                return tokens;
+            }
+        }
+    }
+
+    #[test]
+    fn test_line_endings_lex_identically() {
+        let unix = "declare add = proc(a, b) {\n\ta + b // sum\n};\n\nadd(1, 2);\n";
+        let windows = unix.replace('\n', "\r\n");
+
+        let tokens = lines_and_columns(unix);
+        assert_eq!(lines_and_columns(&windows), tokens);
+        assert!(tokens.iter().all(|(t, ..)| *t != TokenType::ILLEGAL));
+        assert_eq!(
+            tokens.last().unwrap(),
+            &(TokenType::EOF, "".to_owned(), 6, 1)
+        );
+    }
+
+    #[test]
+    fn test_unicode_whitespace_is_skipped() {
+        let tokens = lines_and_columns("declare\u{a0}x\u{2003}=\u{3000}1;\u{2028}");
+
+        let token_types: Vec<TokenType> = tokens.into_iter().map(|(t, ..)| t).collect();
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::LET,
+                TokenType::IDENT,
+                TokenType::ASSIGN,
+                TokenType::INT,
+                TokenType::SEMICOLON,
+                TokenType::EOF,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unicode_identifiers() {
+        let tokens = lines_and_columns("declare año = \"€\";\nnaïve_π2 + 日本\ncafe\u{301} x½");
+
+        let expected = [
+            (TokenType::LET, "declare", 1, 1),
+            (TokenType::IDENT, "año", 1, 9),
+            (TokenType::ASSIGN, "=", 1, 13),
+            (TokenType::STRING, "€", 1, 15),
+            (TokenType::SEMICOLON, ";", 1, 18),
+            (TokenType::IDENT, "naïve_π2", 2, 1),
+            (TokenType::PLUS, "+", 2, 10),
+            (TokenType::IDENT, "日本", 2, 12),
+            (TokenType::IDENT, "cafe\u{301}", 3, 1),
+            (TokenType::IDENT, "x", 3, 7),
+            (TokenType::ILLEGAL, "½", 3, 8),
+            (TokenType::EOF, "", 3, 9),
+        ];
+        let expected: Vec<(TokenType, String, usize, usize)> = expected
+            .iter()
+            .map(|(t, literal, line, column)| (t.clone(), literal.to_string(), *line, *column))
+            .collect();
+        assert_eq!(tokens, expected);
+    }
+
+    /// Lexes 1 MB and 4 MB of source and checks the time grows linearly.
+    /// Run it with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_lexing_is_linear() {
+        let chunk = "/// Doubles.\ndeclare dóble = proc(x) { x * 2 }; // ok\ndóble(21) >= 42;\n";
+        let time = |bytes: usize| {
+            let source = chunk.repeat(bytes / chunk.len());
+            let started = std::time::Instant::now();
+            let mut lexer = Lexer::new(source);
+            let mut count = 0;
+            while lexer.next_token().token_type != TokenType::EOF {
+                count += 1;
+            }
+            let elapsed = started.elapsed();
+            println!("{} bytes, {} tokens: {:?}", bytes, count, elapsed);
+            return elapsed.as_secs_f64();
+        };
+
+        let small = time(1 << 20);
+        let large = time(4 << 20);
+        assert!(
+            large < small * 8.0,
+            "lexing 4x the input took {:.1}x as long",
+            large / small
+        );
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let mut lexer = Lexer::new(
+            r#""tab\there\nquote \" slash \\ \u{48}\u{1F600}\u{e9} cr\r nul\0""#.to_owned(),
+        );
+
+        let token = lexer.next_token();
+        assert_eq!(token.token_type, TokenType::STRING);
+        assert_eq!(
+            token.literal,
+            "tab\there\nquote \" slash \\ H\u{1F600}\u{e9} cr\r nul\0"
+        );
+        assert!(lexer.take_errors().is_empty());
+    }
+
+    #[test]
+    fn test_raw_strings() {
+        let source = "r\"C:\\new\" r#\"say \"hi\"\nover \\n lines\"# r";
+        let mut lexer = Lexer::new(source.to_owned());
+
+        let expected = [
+            (TokenType::STRING, "C:\\new"),
+            (TokenType::STRING, "say \"hi\"\nover \\n lines"),
+            (TokenType::IDENT, "r"),
+            (TokenType::EOF, ""),
+        ];
+        for (token_type, literal) in expected {
+            let token = lexer.next_token();
+            assert_eq!(token.token_type, token_type);
+            assert_eq!(token.literal, literal);
+        }
+        assert!(lexer.take_errors().is_empty());
+    }
+
+    #[test]
+    fn test_interpolation_segments() {
+        let mut lexer = Lexer::new(r#""a ${x + {1}} b ${"c ${d}"}\${e}""#.to_owned());
+
+        let expected = [
+            (TokenType::STRINGSTART, "a "),
+            (TokenType::IDENT, "x"),
+            (TokenType::PLUS, "+"),
+            (TokenType::LBRACE, "{"),
+            (TokenType::INT, "1"),
+            (TokenType::RBRACE, "}"),
+            (TokenType::STRINGMIDDLE, " b "),
+            (TokenType::STRINGSTART, "c "),
+            (TokenType::IDENT, "d"),
+            (TokenType::STRINGEND, ""),
+            (TokenType::STRINGEND, "${e}"),
+            (TokenType::EOF, ""),
+        ];
+        for (token_type, literal) in expected {
+            let token = lexer.next_token();
+            assert_eq!(token.token_type, token_type);
+            assert_eq!(token.literal, literal);
+        }
+        assert!(lexer.take_errors().is_empty());
+    }
+
+    #[test]
+    fn test_number_literals() {
+        let mut lexer =
+            Lexer::new("42 1_000 0xff 0O17 0b1010 3.14 1e-9 2.5E+3 6.0e1_0 7e x1".to_owned());
+
+        let expected = [
+            (TokenType::INT, "42"),
+            (TokenType::INT, "1_000"),
+            (TokenType::INT, "0xff"),
+            (TokenType::INT, "0O17"),
+            (TokenType::INT, "0b1010"),
+            (TokenType::FLOAT, "3.14"),
+            (TokenType::FLOAT, "1e-9"),
+            (TokenType::FLOAT, "2.5E+3"),
+            (TokenType::FLOAT, "6.0e1_0"),
+            (TokenType::INT, "7"),
+            (TokenType::IDENT, "e"),
+            (TokenType::IDENT, "x1"),
+            (TokenType::EOF, ""),
+        ];
+        for (token_type, literal) in expected {
+            let token = lexer.next_token();
+            assert_eq!(token.token_type, token_type);
+            assert_eq!(token.literal, literal);
+        }
+    }
+
+    #[test]
+    fn test_string_errors() {
+        let tests = [
+            ("\"open", "E0005", "unterminated string", (1, 2)),
+            ("r##\"open\"#", "E0005", "unterminated string", (1, 5)),
+            (
+                "\"a\\qb\"",
+                "E0006",
+                "unknown character escape: \\q",
+                (3, 5),
+            ),
+            ("\"\\u41\"", "E0007", "invalid unicode escape", (2, 4)),
+            ("\"\\u{}\"", "E0007", "invalid unicode escape", (2, 6)),
+            (
+                "\"\\u{1234567}\"",
+                "E0007",
+                "invalid unicode escape",
+                (2, 13),
+            ),
+            ("\"\\u{D800}\"", "E0007", "invalid unicode escape", (2, 10)),
+            ("\"\\u{41\"", "E0007", "invalid unicode escape", (2, 7)),
+            ("1 # 2", "E0008", "unknown start of token: #", (3, 4)),
+        ];
+
+        for (source, code, message, (start, end)) in tests {
+            let mut lexer = Lexer::new(source.to_owned());
+            while lexer.next_token().token_type != TokenType::EOF {}
+
+            let errors = lexer.take_errors();
+            assert_eq!(errors.len(), 1, "source: {}", source);
+            assert_eq!(errors[0].code.as_deref(), Some(code), "source: {}", source);
+            assert_eq!(errors[0].message, message, "source: {}", source);
+            let span = errors[0].primary.span;
+            assert_eq!(
+                (span.start.column, span.end.column),
+                (start, end),
+                "source: {}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let mut lexer = Lexer::new("declare x\n= 10;".to_owned());
+        let expected = [
+            (TokenType::LET, (0, 1, 1), (7, 1, 8)),
+            (TokenType::IDENT, (8, 1, 9), (9, 1, 10)),
+            (TokenType::ASSIGN, (10, 2, 1), (11, 2, 2)),
+            (TokenType::INT, (12, 2, 3), (14, 2, 5)),
+            (TokenType::SEMICOLON, (14, 2, 5), (15, 2, 6)),
+            (TokenType::EOF, (15, 2, 6), (15, 2, 6)),
+        ];
+
+        for (token_type, (offset, line, column), (end_offset, end_line, end_column)) in expected {
+            let token = lexer.next_token();
+
+            assert_eq!(token.token_type, token_type);
+            assert_eq!(
+                token.span,
+                Span::new(
+                    Position::new(offset, line, column),
+                    Position::new(end_offset, end_line, end_column)
+                ),
+                "token: {:?}",
+                token
+            );
         }
     }
 }
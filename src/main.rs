@@ -1,27 +1,116 @@
+#![allow(clippy::needless_return, clippy::upper_case_acronyms)]
+
+use std::io::Read;
+
 use clap::StructOpt;
+use colored::Colorize;
 use repl::start_rpl;
 
-use crate::{parser::Parser, repl::Cli};
+use crate::{
+    environment::Environment,
+    object::Object,
+    parser::Parser,
+    repl::{Cli, Command},
+};
 
 mod ast;
+mod builtins;
+mod diagnostic;
+mod environment;
+mod evaluator;
+mod highlight;
 mod lexer;
+mod object;
 mod parser;
 mod repl;
 mod token;
 
-fn main() {
-    let lexer = lexer::Lexer::new("".to_owned());
-    let parser = Parser::new(lexer);
-    let program = parser.parse_program();
+const EXIT_PARSE_ERROR: i32 = 65;
+const EXIT_RUNTIME_ERROR: i32 = 70;
+const EXIT_OS_ERROR: i32 = 71;
+const EXIT_IO_ERROR: i32 = 74;
+/// The code Rust itself exits with after a panic.
+const EXIT_PANIC: i32 = 101;
 
+fn main() {
     let args = Cli::parse();
 
-    match args.file {
-        Some(expr) => {
-            println!("File ");
+    // Deeply recursive scripts need more stack than the main thread has.
+    let spawned = std::thread::Builder::new()
+        .stack_size(evaluator::STACK_SIZE)
+        .spawn(move || match args.command {
+            Some(Command::Run { file, args }) => run(&file, args),
+            None => {
+                start_rpl();
+                0
+            }
+        });
+
+    let interpreter = match spawned {
+        Ok(interpreter) => interpreter,
+        Err(error) => {
+            eprintln!(
+                "{}: could not start the interpreter: {}",
+                "error".red().bold(),
+                error
+            );
+            std::process::exit(EXIT_OS_ERROR);
+        }
+    };
+
+    std::process::exit(interpreter.join().unwrap_or(EXIT_PANIC));
+}
+
+fn run(file: &str, args: Vec<String>) -> i32 {
+    let (source, file_name) = match read_source(file) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!(
+                "{}: could not read {}: {}",
+                "error".red().bold(),
+                file,
+                error
+            );
+            return EXIT_IO_ERROR;
+        }
+    };
+
+    let lexer = lexer::Lexer::new(source.clone());
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+
+    if !parser.errors().is_empty() {
+        for error in parser.errors() {
+            eprint!("{}", error.render(&source, &file_name));
         }
-        None => {
-            start_rpl();
+        return EXIT_PARSE_ERROR;
+    }
+
+    let env = Environment::new();
+    let args = args.into_iter().map(Object::String).collect();
+    env.borrow_mut().set("args".to_owned(), Object::array(args));
+
+    match evaluator::evaluate(&program, &env) {
+        Object::Error(message) => {
+            eprintln!("{}: {}", "error".red().bold(), message);
+            return EXIT_RUNTIME_ERROR;
         }
+        Object::Null => {}
+        result => println!("{}", result.inspect()),
     }
+
+    return 0;
+}
+
+/// Reads the script at `file`, or stdin when `file` is `-`, returning its
+/// source and the name used in diagnostics.
+fn read_source(file: &str) -> std::io::Result<(String, String)> {
+    if file == "-" {
+        let mut source = String::new();
+        std::io::stdin().read_to_string(&mut source)?;
+        return Ok((source, "<stdin>".to_owned()));
+    }
+
+    let source = std::fs::read_to_string(file)?;
+    return Ok((source, file.to_owned()));
 }
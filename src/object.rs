@@ -0,0 +1,196 @@
+use std::{collections::BTreeMap, rc::Rc};
+
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+use crate::{
+    ast::{AstNode, Block, Identifier},
+    environment::Env,
+};
+
+pub type BuiltinFunction = fn(Vec<Object>) -> Object;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ObjectType {
+    ARRAY,
+    BOOLEAN,
+    BREAK,
+    BUILTIN,
+    CONTINUE,
+    ERROR,
+    FLOAT,
+    FUNCTION,
+    INTEGER,
+    MAP,
+    NULL,
+    RANGE,
+    RETURN,
+    STRING,
+}
+
+#[derive(Clone)]
+pub enum Object {
+    /// Arrays and maps are values: every copy behaves as if it were separate.
+    /// The elements are shared until one copy changes, which then copies
+    /// them first (see `Rc::make_mut`), so reading and passing them is cheap.
+    Array(Rc<Vec<Object>>),
+    /// An integer too large for `Integer`. Arithmetic moves between the two
+    /// on its own, so this is always outside the `isize` range.
+    BigInteger(BigInt),
+    Boolean(bool),
+    /// Produced by `break` and unwound to the innermost loop, like `Return`
+    /// is to the enclosing call.
+    Break,
+    Builtin {
+        name: &'static str,
+        function: BuiltinFunction,
+    },
+    Continue,
+    Error(String),
+    Float(f64),
+    Function {
+        parameters: Vec<Identifier>,
+        body: Rc<Block>,
+        env: Env,
+    },
+    Integer(isize),
+    Map(Rc<BTreeMap<HashKey, Object>>),
+    Null,
+    /// The integers from `start` up to, but not including, `end`.
+    Range {
+        start: isize,
+        end: isize,
+    },
+    Return(Box<Object>),
+    String(String),
+}
+
+impl Object {
+    pub fn object_type(&self) -> ObjectType {
+        match self {
+            Object::Array(_) => ObjectType::ARRAY,
+            Object::BigInteger(_) => ObjectType::INTEGER,
+            Object::Boolean(_) => ObjectType::BOOLEAN,
+            Object::Break => ObjectType::BREAK,
+            Object::Builtin { .. } => ObjectType::BUILTIN,
+            Object::Continue => ObjectType::CONTINUE,
+            Object::Error(_) => ObjectType::ERROR,
+            Object::Float(_) => ObjectType::FLOAT,
+            Object::Function { .. } => ObjectType::FUNCTION,
+            Object::Integer(_) => ObjectType::INTEGER,
+            Object::Map(_) => ObjectType::MAP,
+            Object::Null => ObjectType::NULL,
+            Object::Range { .. } => ObjectType::RANGE,
+            Object::Return(_) => ObjectType::RETURN,
+            Object::String(_) => ObjectType::STRING,
+        }
+    }
+
+    pub fn inspect(&self) -> String {
+        match self {
+            Object::Array(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|e| e.inspect())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!("[{}]", elements)
+            }
+            Object::BigInteger(value) => value.to_string(),
+            Object::Boolean(value) => value.to_string(),
+            Object::Break => "break".to_owned(),
+            Object::Builtin { name, .. } => format!("builtin {}", name),
+            Object::Continue => "continue".to_owned(),
+            Object::Error(message) => format!("Error: {}", message),
+            Object::Float(value) => format!("{:?}", value),
+            Object::Function {
+                parameters, body, ..
+            } => {
+                let param_list = parameters
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!("proc({}) {{\n{}\n}}", param_list, body.to_string())
+            }
+            Object::Integer(value) => value.to_string(),
+            Object::Map(pairs) => {
+                let pairs = pairs
+                    .iter()
+                    .map(|(key, value)| {
+                        format!("{}: {}", key.to_object().inspect(), value.inspect())
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!("{{{}}}", pairs)
+            }
+            Object::Null => "null".to_owned(),
+            Object::Range { start, end } => format!("{}..{}", start, end),
+            Object::Return(value) => value.inspect(),
+            Object::String(value) => value.clone(),
+        }
+    }
+
+    pub fn array(elements: Vec<Object>) -> Object {
+        return Object::Array(Rc::new(elements));
+    }
+
+    pub fn map(pairs: BTreeMap<HashKey, Object>) -> Object {
+        return Object::Map(Rc::new(pairs));
+    }
+
+    /// Wraps an integer, using `BigInteger` only when it does not fit in
+    /// an `isize`.
+    pub fn from_big_int(value: BigInt) -> Object {
+        return match value.to_isize() {
+            Some(value) => Object::Integer(value),
+            None => Object::BigInteger(value),
+        };
+    }
+
+    pub fn is_error(&self) -> bool {
+        return self.object_type() == ObjectType::ERROR;
+    }
+
+    /// Whether evaluation has to stop and hand this value up unchanged: an
+    /// error, a `return` on its way out to the enclosing call, or a `break`
+    /// or `continue` on its way out to the enclosing loop.
+    pub fn is_abrupt(&self) -> bool {
+        return matches!(
+            self,
+            Object::Break | Object::Continue | Object::Error(_) | Object::Return(_)
+        );
+    }
+}
+
+/// The runtime values that can be used as map keys. Maps keep their keys
+/// sorted, so they always print and iterate in the same order.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HashKey {
+    Boolean(bool),
+    Integer(BigInt),
+    String(String),
+}
+
+impl HashKey {
+    pub fn from_object(object: &Object) -> Result<HashKey, Object> {
+        return match object {
+            Object::Boolean(value) => Ok(HashKey::Boolean(*value)),
+            Object::Integer(value) => Ok(HashKey::Integer(BigInt::from(*value))),
+            Object::BigInteger(value) => Ok(HashKey::Integer(value.clone())),
+            Object::String(value) => Ok(HashKey::String(value.clone())),
+            other => Err(Object::Error(format!(
+                "unusable as map key: {:?}",
+                other.object_type()
+            ))),
+        };
+    }
+
+    pub fn to_object(&self) -> Object {
+        return match self {
+            HashKey::Boolean(value) => Object::Boolean(*value),
+            HashKey::Integer(value) => Object::from_big_int(value.clone()),
+            HashKey::String(value) => Object::String(value.clone()),
+        };
+    }
+}
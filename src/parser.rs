@@ -1,29 +1,80 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, rc::Rc};
+
+use num_bigint::BigInt;
 
 use crate::{
-    ast::{Expression, ExpressionStatement, LetStatement, Program, ReturnStatement, Statement},
+    ast::{
+        ArrayLiteral, Assign, AstNode, Block, Boolean, BreakStatement, Call, ContinueStatement,
+        Expression, ExpressionStatement, Field, Float, ForStatement, Function, Identifier, If,
+        Index, Infix, Integer, IntegerValue, Interpolation, LetStatement, MapLiteral, Prefix,
+        Program, ReturnStatement, Slice, Statement, StringLiteral, WhileStatement,
+    },
+    diagnostic::Diagnostic,
     lexer::Lexer,
-    token::{Token, TokenType},
+    token::{Position, Span, Token, TokenType},
 };
 
-pub type PrefixParseFn = fn() -> Option<Box<dyn Expression>>;
-pub type InfixParseFn = fn(&mut Parser, Box<dyn Expression>) -> Option<Box<dyn Expression>>;
+pub type PrefixParseFn = fn(&mut Parser) -> Option<Expression>;
+pub type InfixParseFn = fn(&mut Parser, Expression) -> Option<Expression>;
 
 pub type PrefixParseFns = HashMap<TokenType, PrefixParseFn>;
 pub type InfixParseFns = HashMap<TokenType, InfixParseFn>;
 
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Precedence {
+    LOWEST = 1,
+    ASSIGN = 2,
+    RANGE = 3,
+    OR = 4,
+    AND = 5,
+    EQUALS = 6,
+    LESSGREATER = 7,
+    SUM = 8,
+    PRODUCT = 9,
+    PREFIX = 10,
+    CALL = 11,
+    INDEX = 12,
+}
+
+pub fn lookup_precedence(token_type: &TokenType) -> Precedence {
+    match token_type {
+        TokenType::ASSIGN
+        | TokenType::PLUSASSIGN
+        | TokenType::MINUSASSIGN
+        | TokenType::MULTIPLICATIONASSIGN
+        | TokenType::DIVISIONASSIGN => Precedence::ASSIGN,
+        TokenType::RANGE => Precedence::RANGE,
+        TokenType::OR => Precedence::OR,
+        TokenType::AND => Precedence::AND,
+        TokenType::EQ | TokenType::NOTEQ => Precedence::EQUALS,
+        TokenType::LT | TokenType::GT | TokenType::LTEQ | TokenType::GTEQ => {
+            Precedence::LESSGREATER
+        }
+        TokenType::PLUS | TokenType::MINUS => Precedence::SUM,
+        TokenType::DIVISION | TokenType::MULTIPLICATION | TokenType::MODULO => Precedence::PRODUCT,
+        TokenType::LPAREN => Precedence::CALL,
+        TokenType::LBRACKET | TokenType::DOT => Precedence::INDEX,
+        _ => Precedence::LOWEST,
+    }
+}
+
+const DECLARE_HELP: &str = "bindings are declared as `declare <name> = <expression>;`";
+
 pub struct Parser {
     lexer: Lexer,
     current_token: Option<Token>,
     peek_token: Option<Token>,
-    errors: Vec<String>,
+    errors: Vec<Diagnostic>,
     prefix_parse_fns: Option<PrefixParseFns>,
     infix_parse_fns: Option<InfixParseFns>,
+    /// How many loops enclose the current token, so `break` and `continue`
+    /// can be rejected outside of them. A `proc` body starts again at zero.
+    loop_depth: usize,
 }
 
 impl Parser {
     pub fn new(lexer: Lexer) -> Parser {
-        Parser {
+        let mut parser = Parser {
             lexer,
             current_token: None,
             peek_token: None,
@@ -31,23 +82,28 @@ impl Parser {
 
             prefix_parse_fns: None,
             infix_parse_fns: None,
-        }
+            loop_depth: 0,
+        };
+
+        parser.advance_tokens();
+        parser.advance_tokens();
+
+        return parser;
     }
 
-    pub fn errors(&self) -> Vec<String> {
+    pub fn errors(&self) -> Vec<Diagnostic> {
         self.errors.clone()
     }
 
-    pub fn parse_program(&self) -> Program {
+    pub fn parse_program(&mut self) -> Program {
         self.prefix_parse_fns = Some(self.register_prefix_fns());
         self.infix_parse_fns = Some(self.register_infix_fns());
 
-        let program = Program::new(vec![]);
+        let mut program = Program::new(vec![]);
 
-        while self.current_token.unwrap().token_type != TokenType::EOF {
-            let stmt = self.parse_statement();
-            if stmt.is_some() {
-                program.statements.push(stmt.unwrap());
+        while !self.current_token_is(TokenType::EOF) {
+            if let Some(stmt) = self.parse_statement_or_recover() {
+                program.statements.push(stmt);
             }
             self.advance_tokens();
         }
@@ -55,76 +111,371 @@ impl Parser {
         return program;
     }
 
-    fn parse_infix_expression(&self, left: Box<dyn Expression>) -> Option<Box<dyn Expression>> {
-        unimplemented!();
+    /// Parses a statement and, if that fails, skips ahead to the next
+    /// statement boundary so the following statements are still parsed.
+    fn parse_statement_or_recover(&mut self) -> Option<Statement> {
+        let error_count = self.errors.len();
+        let statement = self.parse_statement();
+
+        if statement.is_none() && self.errors.len() > error_count {
+            self.synchronize();
+        }
+
+        return statement;
+    }
+
+    /// Leaves the parser on the last token of the broken statement: a `;`,
+    /// the token before a `}` closing the enclosing block, or the token
+    /// before a keyword such as `declare` or `while` that starts a new
+    /// statement. A statement that broke on the closing `}` itself stays
+    /// there, so the block still ends at it.
+    fn synchronize(&mut self) {
+        let mut depth = 0;
+
+        if self.current_token_is(TokenType::RBRACE) {
+            return;
+        }
+
+        loop {
+            if self.current_token_is(TokenType::EOF) {
+                return;
+            }
+
+            if self.current_token_is(TokenType::LBRACE) {
+                depth += 1;
+            } else if self.current_token_is(TokenType::RBRACE) && depth > 0 {
+                depth -= 1;
+            }
+
+            if depth == 0 {
+                if self.current_token_is(TokenType::SEMICOLON) {
+                    return;
+                }
+
+                let peek_type = &self.peek_token.as_ref().unwrap().token_type;
+                if matches!(
+                    peek_type,
+                    TokenType::LET
+                        | TokenType::RETURN
+                        | TokenType::FUNCTION
+                        | TokenType::WHILE
+                        | TokenType::FOR
+                        | TokenType::BREAK
+                        | TokenType::CONTINUE
+                        | TokenType::RBRACE
+                        | TokenType::EOF
+                ) {
+                    return;
+                }
+            }
+
+            self.advance_tokens();
+        }
     }
 
     fn register_infix_fns(&self) -> InfixParseFns {
-        let mut infix_parse_fns: HashMap<TokenType, InfixParseFn> = HashMap::new();
-        unimplemented!();
-        infix_parse_fns.insert(TokenType::PLUS, &self.parse_infix_expression);
-        //return infix_parse_fns;
+        let mut infix_parse_fns: InfixParseFns = HashMap::new();
+        infix_parse_fns.insert(TokenType::PLUS, Parser::parse_infix_expression);
+        infix_parse_fns.insert(TokenType::MINUS, Parser::parse_infix_expression);
+        infix_parse_fns.insert(TokenType::DIVISION, Parser::parse_infix_expression);
+        infix_parse_fns.insert(TokenType::MULTIPLICATION, Parser::parse_infix_expression);
+        infix_parse_fns.insert(TokenType::MODULO, Parser::parse_infix_expression);
+        infix_parse_fns.insert(TokenType::EQ, Parser::parse_infix_expression);
+        infix_parse_fns.insert(TokenType::NOTEQ, Parser::parse_infix_expression);
+        infix_parse_fns.insert(TokenType::LT, Parser::parse_infix_expression);
+        infix_parse_fns.insert(TokenType::GT, Parser::parse_infix_expression);
+        infix_parse_fns.insert(TokenType::LTEQ, Parser::parse_infix_expression);
+        infix_parse_fns.insert(TokenType::GTEQ, Parser::parse_infix_expression);
+        infix_parse_fns.insert(TokenType::AND, Parser::parse_infix_expression);
+        infix_parse_fns.insert(TokenType::OR, Parser::parse_infix_expression);
+        infix_parse_fns.insert(TokenType::RANGE, Parser::parse_infix_expression);
+        infix_parse_fns.insert(TokenType::ASSIGN, Parser::parse_assign);
+        infix_parse_fns.insert(TokenType::PLUSASSIGN, Parser::parse_assign);
+        infix_parse_fns.insert(TokenType::MINUSASSIGN, Parser::parse_assign);
+        infix_parse_fns.insert(TokenType::MULTIPLICATIONASSIGN, Parser::parse_assign);
+        infix_parse_fns.insert(TokenType::DIVISIONASSIGN, Parser::parse_assign);
+        infix_parse_fns.insert(TokenType::LPAREN, Parser::parse_call);
+        infix_parse_fns.insert(TokenType::LBRACKET, Parser::parse_index);
+        infix_parse_fns.insert(TokenType::DOT, Parser::parse_field);
+        return infix_parse_fns;
     }
 
     fn register_prefix_fns(&self) -> PrefixParseFns {
-        let mut prefix_parse_fns = HashMap::new();
-        prefix_parse_fns.insert(TokenType::PLUS, self.parse_infix_expression);
-        unimplemented!();
-        //return prefix_parse_fns;
+        let mut prefix_parse_fns: PrefixParseFns = HashMap::new();
+        prefix_parse_fns.insert(TokenType::FALSE, Parser::parse_boolean);
+        prefix_parse_fns.insert(TokenType::FLOAT, Parser::parse_float);
+        prefix_parse_fns.insert(TokenType::FUNCTION, Parser::parse_function);
+        prefix_parse_fns.insert(TokenType::IDENT, Parser::parse_identifier);
+        prefix_parse_fns.insert(TokenType::IF, Parser::parse_if);
+        prefix_parse_fns.insert(TokenType::INT, Parser::parse_integer);
+        prefix_parse_fns.insert(TokenType::LBRACE, Parser::parse_map_literal);
+        prefix_parse_fns.insert(TokenType::LBRACKET, Parser::parse_array_literal);
+        prefix_parse_fns.insert(TokenType::LPAREN, Parser::parse_grouped_expression);
+        prefix_parse_fns.insert(TokenType::MINUS, Parser::parse_prefix_expression);
+        prefix_parse_fns.insert(TokenType::NEGATION, Parser::parse_prefix_expression);
+        prefix_parse_fns.insert(TokenType::STRING, Parser::parse_string_literal);
+        prefix_parse_fns.insert(TokenType::STRINGSTART, Parser::parse_interpolation);
+        prefix_parse_fns.insert(TokenType::TRUE, Parser::parse_boolean);
+        return prefix_parse_fns;
     }
 
     fn advance_tokens(&mut self) {
         self.current_token = self.peek_token.take();
         self.peek_token = Some(self.lexer.next_token());
+        self.errors.append(&mut self.lexer.take_errors());
+    }
+
+    fn current_token(&self) -> Token {
+        return self.current_token.clone().unwrap();
+    }
+
+    fn current_token_is(&self, token_type: TokenType) -> bool {
+        return self.current_token.as_ref().unwrap().token_type == token_type;
+    }
+
+    fn peek_token_is(&self, token_type: TokenType) -> bool {
+        return self.peek_token.as_ref().unwrap().token_type == token_type;
+    }
+
+    fn current_precedence(&self) -> Precedence {
+        return lookup_precedence(&self.current_token.as_ref().unwrap().token_type);
+    }
+
+    fn peek_precedence(&self) -> Precedence {
+        return lookup_precedence(&self.peek_token.as_ref().unwrap().token_type);
+    }
+
+    fn expected_token(&mut self, token_type: TokenType) -> bool {
+        if self.peek_token_is(token_type.clone()) {
+            self.advance_tokens();
+            return true;
+        }
+
+        self.expected_token_error(token_type);
+        return false;
+    }
+
+    fn expected_token_error(&mut self, token_type: TokenType) {
+        let peek_token = self.peek_token.as_ref().unwrap();
+        let diagnostic = Diagnostic::error(
+            format!(
+                "expected next token to be {:?}, got {:?} instead",
+                token_type, peek_token.token_type
+            ),
+            peek_token.span,
+        )
+        .with_code("E0001")
+        .with_primary_label(&format!("expected {:?}", token_type));
+
+        self.errors.push(diagnostic);
+    }
+
+    fn add_help_to_last_error(&mut self, help: &str) {
+        if let Some(diagnostic) = self.errors.pop() {
+            self.errors.push(diagnostic.with_help(help));
+        }
+    }
+
+    fn add_label_to_last_error(&mut self, token: &Token, message: &str) {
+        if let Some(diagnostic) = self.errors.pop() {
+            self.errors.push(diagnostic.with_label(token.span, message));
+        }
+    }
+
+    fn parse_statement(&mut self) -> Option<Statement> {
+        match self.current_token.as_ref().unwrap().token_type {
+            TokenType::LET => self.parse_let_statement(),
+            TokenType::RETURN => self.parse_return_statement(),
+            TokenType::WHILE => self.parse_while_statement(),
+            TokenType::FOR => self.parse_for_statement(),
+            TokenType::BREAK | TokenType::CONTINUE => self.parse_loop_control_statement(),
+            _ => self.parse_expression_statement(),
+        }
+    }
+
+    fn parse_let_statement(&mut self) -> Option<Statement> {
+        let token = self.current_token();
+
+        if !self.expected_token(TokenType::IDENT) {
+            self.add_help_to_last_error(DECLARE_HELP);
+            return None;
+        }
+
+        let name_token = self.current_token();
+        let name = Identifier::new(name_token.clone(), name_token.literal);
+
+        if !self.expected_token(TokenType::ASSIGN) {
+            self.add_help_to_last_error(DECLARE_HELP);
+            return None;
+        }
+
+        self.advance_tokens();
+        let value = self.parse_expression(Precedence::LOWEST)?;
+
+        if self.peek_token_is(TokenType::SEMICOLON) {
+            self.advance_tokens();
+        }
+
+        return Some(Statement::Let(LetStatement {
+            token,
+            name,
+            value: Box::new(value),
+        }));
+    }
+
+    fn parse_return_statement(&mut self) -> Option<Statement> {
+        let token = self.current_token();
+
+        self.advance_tokens();
+        let return_value = self.parse_expression(Precedence::LOWEST)?;
+
+        if self.peek_token_is(TokenType::SEMICOLON) {
+            self.advance_tokens();
+        }
+
+        return Some(Statement::Return(ReturnStatement {
+            token,
+            return_value: Box::new(return_value),
+        }));
+    }
+
+    fn parse_while_statement(&mut self) -> Option<Statement> {
+        let token = self.current_token();
+
+        self.advance_tokens();
+        let condition = self.parse_expression(Precedence::LOWEST)?;
+
+        if !self.expected_token(TokenType::LBRACE) {
+            return None;
+        }
+
+        let body = self.parse_loop_body();
+
+        if self.peek_token_is(TokenType::SEMICOLON) {
+            self.advance_tokens();
+        }
+
+        return Some(Statement::While(WhileStatement {
+            token,
+            condition: Box::new(condition),
+            body,
+        }));
     }
 
-    fn parse_statement(&mut self) -> Option<Box<dyn Statement>> {
-        match self.current_token.unwrap().token_type {
-            TokenType::LET => Some(self.parse_let_statement().unwrap()),
-            TokenType::RETURN => Some(self.parse_return_statement().unwrap()),
-            _ => Some(self.parse_expression_statement().unwrap()),
+    /// Parses `for name in iterable { body }`.
+    fn parse_for_statement(&mut self) -> Option<Statement> {
+        let token = self.current_token();
+
+        if !self.expected_token(TokenType::IDENT) {
+            return None;
+        }
+
+        let variable_token = self.current_token();
+        let variable = Identifier::new(variable_token.clone(), variable_token.literal);
+
+        if !self.expected_token(TokenType::IN) {
+            return None;
         }
+
+        self.advance_tokens();
+        let iterable = self.parse_expression(Precedence::LOWEST)?;
+
+        if !self.expected_token(TokenType::LBRACE) {
+            return None;
+        }
+
+        let body = self.parse_loop_body();
+
+        if self.peek_token_is(TokenType::SEMICOLON) {
+            self.advance_tokens();
+        }
+
+        return Some(Statement::For(ForStatement {
+            token,
+            variable,
+            iterable: Box::new(iterable),
+            body,
+        }));
+    }
+
+    fn parse_loop_body(&mut self) -> Block {
+        self.loop_depth += 1;
+        let body = self.parse_block();
+        self.loop_depth -= 1;
+        return body;
     }
 
-    fn parse_expression_statement(&mut self) -> Option<Box<ExpressionStatement>> {
-        let expression_statement = ExpressionStatement {
-            token: self.current_token.unwrap().clone(),
-            expression: self.parse_expression(),
+    /// Parses `break` or `continue`, which are only allowed inside a loop.
+    fn parse_loop_control_statement(&mut self) -> Option<Statement> {
+        let token = self.current_token();
+
+        if self.loop_depth == 0 {
+            self.errors.push(
+                Diagnostic::error(format!("`{}` outside of a loop", token.literal), token.span)
+                    .with_code("E0010")
+                    .with_primary_label(&format!("cannot `{}` outside of a loop", token.literal)),
+            );
+            return None;
+        }
+
+        if self.peek_token_is(TokenType::SEMICOLON) {
+            self.advance_tokens();
+        }
+
+        return match token.token_type {
+            TokenType::BREAK => Some(Statement::Break(BreakStatement { token })),
+            _ => Some(Statement::Continue(ContinueStatement { token })),
         };
+    }
 
-        if self.peek_token.unwrap().token_type == TokenType::SEMICOLON {
+    fn parse_expression_statement(&mut self) -> Option<Statement> {
+        let token = self.current_token();
+        let expression = self.parse_expression(Precedence::LOWEST)?;
+
+        if self.peek_token_is(TokenType::SEMICOLON) {
             self.advance_tokens();
         }
-        return Some(Box::new(expression_statement));
+        return Some(Statement::Expression(ExpressionStatement {
+            token,
+            expression: Box::new(expression),
+        }));
     }
 
-    fn parse_expression(&mut self) -> Option<Box<dyn Expression>> {
+    fn parse_expression(&mut self, precedence: Precedence) -> Option<Expression> {
         let prefix_parse_fn = self
             .prefix_parse_fns
+            .as_ref()
             .unwrap()
-            .get(&self.current_token.unwrap().token_type);
+            .get(&self.current_token.as_ref().unwrap().token_type)
+            .copied();
         if prefix_parse_fn.is_none() {
-            self.errors.push(format!(
-                "no prefix parse function for {} found",
-                self.current_token.unwrap().literal
-            ));
-            return None;
-        }
-
-        let prefix_parse_fn = prefix_parse_fn.unwrap();
-        let left_exp = prefix_parse_fn();
+            let token = self.current_token();
+            // The lexer has already explained why this token is illegal.
+            if token.token_type == TokenType::ILLEGAL {
+                return None;
+            }
 
-        if left_exp.is_none() {
+            self.errors.push(
+                Diagnostic::error(
+                    format!("no prefix parse function for {} found", token.literal),
+                    token.span,
+                )
+                .with_code("E0002")
+                .with_primary_label("expected an expression"),
+            );
             return None;
         }
 
-        let mut left_exp = left_exp.unwrap();
+        let prefix_parse_fn = prefix_parse_fn.unwrap();
+        let mut left_exp = prefix_parse_fn(self)?;
 
-        while self.peek_token.unwrap().token_type != TokenType::SEMICOLON {
+        while !self.peek_token_is(TokenType::SEMICOLON) && precedence < self.peek_precedence() {
             let infix_parse_fn = self
                 .infix_parse_fns
+                .as_ref()
                 .unwrap()
-                .get(&self.peek_token.unwrap().token_type);
+                .get(&self.peek_token.as_ref().unwrap().token_type)
+                .copied();
             if infix_parse_fn.is_none() {
                 break;
             }
@@ -132,22 +483,840 @@ impl Parser {
             let infix_parse_fn = infix_parse_fn.unwrap();
             self.advance_tokens();
 
-            let right_exp = infix_parse_fn(left_exp.into().clone());
-            if right_exp.is_none() {
+            left_exp = infix_parse_fn(self, left_exp)?;
+        }
+
+        return Some(left_exp);
+    }
+
+    fn parse_identifier(&mut self) -> Option<Expression> {
+        let token = self.current_token();
+        let value = token.literal.clone();
+        return Some(Expression::Identifier(Identifier::new(token, value)));
+    }
+
+    fn parse_integer(&mut self) -> Option<Expression> {
+        let token = self.current_token();
+        let digits = token.literal.replace('_', "");
+        let (digits, radix) = match digits.get(..2) {
+            Some("0x" | "0X") => (&digits[2..], 16),
+            Some("0o" | "0O") => (&digits[2..], 8),
+            Some("0b" | "0B") => (&digits[2..], 2),
+            _ => (digits.as_str(), 10),
+        };
+
+        match BigInt::parse_bytes(digits.as_bytes(), radix) {
+            Some(value) => Some(Expression::Integer(Integer {
+                token,
+                value: IntegerValue::from_big_int(value),
+            })),
+            None => {
+                self.errors.push(
+                    Diagnostic::error(
+                        format!("could not parse {} as an integer", token.literal),
+                        token.span,
+                    )
+                    .with_code("E0003")
+                    .with_primary_label("expected at least one digit"),
+                );
+                None
+            }
+        }
+    }
+
+    fn parse_float(&mut self) -> Option<Expression> {
+        let token = self.current_token();
+
+        match token.literal.replace('_', "").parse::<f64>() {
+            Ok(value) => Some(Expression::Float(Float { token, value })),
+            Err(_) => {
+                self.errors.push(
+                    Diagnostic::error(
+                        format!("could not parse {} as a float", token.literal),
+                        token.span,
+                    )
+                    .with_code("E0003"),
+                );
+                None
+            }
+        }
+    }
+
+    fn parse_boolean(&mut self) -> Option<Expression> {
+        let token = self.current_token();
+        let value = token.token_type == TokenType::TRUE;
+        return Some(Expression::Boolean(Boolean { token, value }));
+    }
+
+    fn parse_string_literal(&mut self) -> Option<Expression> {
+        let token = self.current_token();
+        let value = token.literal.clone();
+        return Some(Expression::StringLiteral(StringLiteral { token, value }));
+    }
+
+    /// Parses `"a ${x} b ${y} c"`, which the lexer splits into a
+    /// `STRINGSTART`, any number of `STRINGMIDDLE`s and a `STRINGEND`
+    /// with the embedded expressions between them.
+    fn parse_interpolation(&mut self) -> Option<Expression> {
+        let token = self.current_token();
+        let mut parts = vec![];
+
+        loop {
+            parts.push(self.parse_string_literal()?);
+
+            if self.peek_token_is(TokenType::STRINGMIDDLE)
+                || self.peek_token_is(TokenType::STRINGEND)
+            {
+                self.empty_interpolation_error();
                 return None;
             }
 
-            left_exp = right_exp.unwrap();
+            self.advance_tokens();
+            parts.push(self.parse_expression(Precedence::LOWEST)?);
+
+            if self.peek_token_is(TokenType::STRINGMIDDLE) {
+                self.advance_tokens();
+                continue;
+            }
+            if !self.expected_token(TokenType::STRINGEND) {
+                self.add_label_to_last_error(&token, "string opened here");
+                return None;
+            }
+            break;
         }
 
-        return Some(left_exp);
+        parts.push(self.parse_string_literal()?);
+        let span = token.span.merge(self.current_token().span);
+        return Some(Expression::Interpolation(Interpolation {
+            token,
+            parts,
+            span,
+        }));
+    }
+
+    /// Reports a `${}` with nothing between the braces. The current token
+    /// ends with `${` and the peek token starts with `}`.
+    fn empty_interpolation_error(&mut self) {
+        let opening = self.current_token().span.end;
+        let closing = self.peek_token.as_ref().unwrap().span.start;
+        let span = Span::new(
+            Position::new(opening.offset - 2, opening.line, opening.column - 2),
+            Position::new(closing.offset + 1, closing.line, closing.column + 1),
+        );
+
+        self.errors.push(
+            Diagnostic::error("empty interpolation".to_owned(), span)
+                .with_code("E0011")
+                .with_primary_label("expected an expression between the braces")
+                .with_help("to write a literal `${`, escape it as `\\${`"),
+        );
+    }
+
+    fn parse_prefix_expression(&mut self) -> Option<Expression> {
+        let token = self.current_token();
+        let operator = token.literal.clone();
+
+        self.advance_tokens();
+        let right = self.parse_expression(Precedence::PREFIX)?;
+
+        return Some(Expression::Prefix(Prefix {
+            token,
+            operator,
+            right: Box::new(right),
+        }));
+    }
+
+    fn parse_infix_expression(&mut self, left: Expression) -> Option<Expression> {
+        let token = self.current_token();
+        let operator = token.literal.clone();
+        let precedence = self.current_precedence();
+
+        self.advance_tokens();
+        let right = self.parse_expression(precedence)?;
+
+        return Some(Expression::Infix(Infix {
+            token,
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        }));
+    }
+
+    fn parse_grouped_expression(&mut self) -> Option<Expression> {
+        let token = self.current_token();
+
+        self.advance_tokens();
+        let expression = self.parse_expression(Precedence::LOWEST)?;
+
+        if !self.expected_token(TokenType::RPAREN) {
+            self.add_label_to_last_error(&token, "unclosed delimiter opened here");
+            return None;
+        }
+
+        return Some(expression);
+    }
+
+    fn parse_if(&mut self) -> Option<Expression> {
+        let token = self.current_token();
+
+        self.advance_tokens();
+        let condition = self.parse_expression(Precedence::LOWEST)?;
+
+        if !self.expected_token(TokenType::LBRACE) {
+            return None;
+        }
+
+        let consequence = self.parse_block();
+        let mut alternative = None;
+
+        if self.peek_token_is(TokenType::ELSE) {
+            self.advance_tokens();
+
+            if !self.expected_token(TokenType::LBRACE) {
+                return None;
+            }
+
+            alternative = Some(self.parse_block());
+        }
+
+        return Some(Expression::If(Box::new(If {
+            token,
+            condition: Box::new(condition),
+            consequence,
+            alternative,
+        })));
+    }
+
+    fn parse_block(&mut self) -> Block {
+        let token = self.current_token();
+        let mut statements = vec![];
+
+        self.advance_tokens();
+
+        while !self.current_token_is(TokenType::RBRACE) && !self.current_token_is(TokenType::EOF) {
+            match self.parse_statement_or_recover() {
+                Some(statement) => statements.push(statement),
+                // Recovery stopped on the `}` that closes this block.
+                None if self.current_token_is(TokenType::RBRACE) => break,
+                None => {}
+            }
+            self.advance_tokens();
+        }
+
+        if self.current_token_is(TokenType::EOF) {
+            let eof = self.current_token();
+            self.errors.push(
+                Diagnostic::error("expected RBRACE, got EOF instead".to_owned(), eof.span)
+                    .with_code("E0001")
+                    .with_primary_label("expected RBRACE")
+                    .with_label(token.span, "unclosed block opened here"),
+            );
+        }
+
+        let span = token.span.merge(self.current_token().span);
+        return Block {
+            token,
+            statements,
+            span,
+        };
+    }
+
+    fn parse_function(&mut self) -> Option<Expression> {
+        let token = self.current_token();
+
+        if !self.expected_token(TokenType::LPAREN) {
+            return None;
+        }
+
+        let parameters = self.parse_function_parameters()?;
+
+        if !self.expected_token(TokenType::LBRACE) {
+            return None;
+        }
+
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        let body = self.parse_block();
+        self.loop_depth = loop_depth;
+
+        return Some(Expression::Function(Function {
+            token,
+            parameters,
+            body: Rc::new(body),
+        }));
+    }
+
+    fn parse_function_parameters(&mut self) -> Option<Vec<Identifier>> {
+        let mut parameters = vec![];
+
+        if self.peek_token_is(TokenType::RPAREN) {
+            self.advance_tokens();
+            return Some(parameters);
+        }
+
+        if !self.expected_token(TokenType::IDENT) {
+            return None;
+        }
+        let token = self.current_token();
+        parameters.push(Identifier::new(token.clone(), token.literal));
+
+        while self.peek_token_is(TokenType::COMMA) {
+            self.advance_tokens();
+
+            if !self.expected_token(TokenType::IDENT) {
+                return None;
+            }
+            let token = self.current_token();
+            parameters.push(Identifier::new(token.clone(), token.literal));
+        }
+
+        if !self.expected_token(TokenType::RPAREN) {
+            return None;
+        }
+
+        return Some(parameters);
+    }
+
+    fn parse_call(&mut self, function: Expression) -> Option<Expression> {
+        let token = self.current_token();
+        let arguments = self.parse_expression_list(TokenType::RPAREN)?;
+        let span = function.span().merge(self.current_token().span);
+
+        return Some(Expression::Call(Call {
+            token,
+            function: Box::new(function),
+            arguments,
+            span,
+        }));
+    }
+
+    /// Parses comma-separated expressions up to and including `end`.
+    fn parse_expression_list(&mut self, end: TokenType) -> Option<Vec<Expression>> {
+        let mut expressions = vec![];
+
+        if self.peek_token_is(end.clone()) {
+            self.advance_tokens();
+            return Some(expressions);
+        }
+
+        self.advance_tokens();
+        expressions.push(self.parse_expression(Precedence::LOWEST)?);
+
+        while self.peek_token_is(TokenType::COMMA) {
+            self.advance_tokens();
+            self.advance_tokens();
+            expressions.push(self.parse_expression(Precedence::LOWEST)?);
+        }
+
+        if !self.expected_token(end) {
+            return None;
+        }
+
+        return Some(expressions);
+    }
+
+    fn parse_array_literal(&mut self) -> Option<Expression> {
+        let token = self.current_token();
+        let elements = self.parse_expression_list(TokenType::RBRACKET)?;
+        let span = token.span.merge(self.current_token().span);
+
+        return Some(Expression::Array(ArrayLiteral {
+            token,
+            elements,
+            span,
+        }));
+    }
+
+    /// Parses `{key: value, ...}`. Blocks only ever follow `if`, `else`,
+    /// `proc(...)` and the head of a `while` or `for` loop, so a `{` anywhere
+    /// an expression is expected is a map.
+    fn parse_map_literal(&mut self) -> Option<Expression> {
+        let token = self.current_token();
+        let mut pairs = vec![];
+
+        while !self.peek_token_is(TokenType::RBRACE) {
+            self.advance_tokens();
+            let key = self.parse_expression(Precedence::LOWEST)?;
+            if !self.expected_token(TokenType::COLON) {
+                return None;
+            }
+
+            self.advance_tokens();
+            let value = self.parse_expression(Precedence::LOWEST)?;
+            pairs.push((key, value));
+
+            if !self.peek_token_is(TokenType::RBRACE) && !self.expected_token(TokenType::COMMA) {
+                return None;
+            }
+        }
+
+        self.advance_tokens();
+        let span = token.span.merge(self.current_token().span);
+        return Some(Expression::Map(MapLiteral { token, pairs, span }));
+    }
+
+    /// Parses `target = value` and the compound forms such as `+=`.
+    /// Assignment binds loosest of all and groups to the right, so
+    /// `a = b = 1` sets both.
+    fn parse_assign(&mut self, target: Expression) -> Option<Expression> {
+        let token = self.current_token();
+        if !matches!(
+            target,
+            Expression::Identifier(_) | Expression::Index(_) | Expression::Field(_)
+        ) {
+            self.errors.push(
+                Diagnostic::error("invalid assignment target".to_owned(), target.span())
+                    .with_code("E0009")
+                    .with_primary_label("cannot assign to this expression")
+                    .with_help(
+                        "only names, elements such as `a[0]` and fields such as `m.key` can be assigned to",
+                    ),
+            );
+            return None;
+        }
+
+        self.advance_tokens();
+        let value = self.parse_expression(Precedence::LOWEST)?;
+
+        return Some(Expression::Assign(Assign {
+            token,
+            target: Box::new(target),
+            value: Box::new(value),
+        }));
+    }
+
+    /// Parses `left.name`, which looks up the key `"name"` in a map.
+    fn parse_field(&mut self, left: Expression) -> Option<Expression> {
+        let token = self.current_token();
+
+        if !self.expected_token(TokenType::IDENT) {
+            return None;
+        }
+
+        let name_token = self.current_token();
+        return Some(Expression::Field(Field {
+            token,
+            left: Box::new(left),
+            field: Identifier::new(name_token.clone(), name_token.literal),
+        }));
+    }
+
+    /// Parses `left[index]`, or a slice `left[start:end]` where either
+    /// bound may be left out.
+    fn parse_index(&mut self, left: Expression) -> Option<Expression> {
+        let token = self.current_token();
+
+        let mut start = None;
+        if !self.peek_token_is(TokenType::COLON) {
+            self.advance_tokens();
+            let index = self.parse_expression(Precedence::LOWEST)?;
+
+            if !self.peek_token_is(TokenType::COLON) {
+                if !self.expected_token(TokenType::RBRACKET) {
+                    return None;
+                }
+                return Some(Expression::Index(Index {
+                    token,
+                    span: left.span().merge(self.current_token().span),
+                    left: Box::new(left),
+                    index: Box::new(index),
+                }));
+            }
+            start = Some(Box::new(index));
+        }
+
+        self.advance_tokens();
+        let mut end = None;
+        if !self.peek_token_is(TokenType::RBRACKET) {
+            self.advance_tokens();
+            end = Some(Box::new(self.parse_expression(Precedence::LOWEST)?));
+        }
+        if !self.expected_token(TokenType::RBRACKET) {
+            return None;
+        }
+
+        return Some(Expression::Slice(Slice {
+            token,
+            span: left.span().merge(self.current_token().span),
+            left: Box::new(left),
+            start,
+            end,
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::{Position, Span};
+
+    fn parse(source: &str) -> Program {
+        let lexer = Lexer::new(source.to_owned());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program();
+
+        assert!(
+            parser.errors().is_empty(),
+            "parser errors for {:?}: {:#?}",
+            source,
+            parser.errors()
+        );
+
+        return program;
     }
 
-    fn parse_let_statement(&mut self) -> Option<Box<LetStatement>> {
-        unimplemented!()
+    #[test]
+    fn test_operator_precedence() {
+        let tests = [
+            ("a", "a"),
+            ("5", "5"),
+            ("true", "true"),
+            ("false", "false"),
+            ("-a * b", "((-a) * b)"),
+            ("!-a", "(!(-a))"),
+            ("!true", "(!true)"),
+            ("!false == true", "((!false) == true)"),
+            ("a + b + c", "((a + b) + c)"),
+            ("a + b - c", "((a + b) - c)"),
+            ("a * b * c", "((a * b) * c)"),
+            ("a * b / c", "((a * b) / c)"),
+            ("a + b / c", "(a + (b / c))"),
+            ("a - b * c", "(a - (b * c))"),
+            ("a + b * c + d / e - f", "(((a + (b * c)) + (d / e)) - f)"),
+            ("5 > 4 == 3 < 4", "((5 > 4) == (3 < 4))"),
+            ("5 < 4 != 3 > 4", "((5 < 4) != (3 > 4))"),
+            (
+                "3 + 4 * 5 == 3 * 1 + 4 * 5",
+                "((3 + (4 * 5)) == ((3 * 1) + (4 * 5)))",
+            ),
+            ("3 > 5 == false", "((3 > 5) == false)"),
+            ("3 < 5 == true", "((3 < 5) == true)"),
+            ("a <= b == b >= a", "((a <= b) == (b >= a))"),
+            ("a || b && c", "(a || (b && c))"),
+            ("a && b || c && d", "((a && b) || (c && d))"),
+            ("a == b && !c", "((a == b) && (!c))"),
+            ("x >= 1 || x + 1 <= 0", "((x >= 1) || ((x + 1) <= 0))"),
+            ("1 + (2 + 3) + 4", "((1 + (2 + 3)) + 4)"),
+            ("(5 + 5) * 2", "((5 + 5) * 2)"),
+            ("2 / (5 + 5)", "(2 / (5 + 5))"),
+            ("-(5 + 5)", "(-(5 + 5))"),
+            ("!(true == true)", "(!(true == true))"),
+            ("a + add(b * c) + d", "((a + add((b * c))) + d)"),
+            (
+                "add(a, b, 1, 2 * 3, 4 + 5, add(6, 7 * 8))",
+                "add(a, b, 1, (2 * 3), (4 + 5), add(6, (7 * 8)))",
+            ),
+            (
+                "add(a + b + c * d / f + g)",
+                "add((((a + b) + ((c * d) / f)) + g))",
+            ),
+            ("a * b; c + d", "(a * b)(c + d)"),
+            ("0xff + 1_000 - 0o17 * 0b1010", "((255 + 1000) - (15 * 10))"),
+            ("3.14 * 2.5E+3 / 1e-9", "((3.14 * 2500.0) / 1e-9)"),
+            ("-1.5", "(-1.5)"),
+            ("a % b * c", "((a % b) * c)"),
+            ("[1, 2 * 3][0]", "([1, (2 * 3)][0])"),
+            ("a * [1, 2][b + c]", "(a * ([1, 2][(b + c)]))"),
+            (
+                "add(a * b[2], b[1], 2 * [1, 2][1])",
+                "add((a * (b[2])), (b[1]), (2 * ([1, 2][1])))",
+            ),
+            ("f(x)[0][-1]", "((f(x)[0])[(-1)])"),
+            ("a[1:3]", "(a[1:3])"),
+            ("a[:-1] + a[i + 1:]", "((a[:(-1)]) + (a[(i + 1):]))"),
+            ("a[:]", "(a[:])"),
+            ("[]", "[]"),
+            ("{\"a\": 1, 2: b + c}", "{a: 1, 2: (b + c)}"),
+            ("{}", "{}"),
+            ("a || b..c && d", "((a || b) .. (c && d))"),
+            ("x = y = 1", "(x = (y = 1))"),
+            ("x += a * b", "(x += (a * b))"),
+            ("a.b[0] -= 1", "(((a.b)[0]) -= 1)"),
+            ("m.a.b *= c /= d", "(((m.a).b) *= (c /= d))"),
+            ("m.f(x) + 1", "((m.f)(x) + 1)"),
+            ("-1..len(a)", "((-1) .. len(a))"),
+            ("m[k] = v", "((m[k]) = v)"),
+            (
+                "m[a][b] = n[c] = d || e",
+                "(((m[a])[b]) = ((n[c]) = (d || e)))",
+            ),
+            (
+                "123456789012345678901234567890 + 0xffffffffffffffffffff",
+                "(123456789012345678901234567890 + 1208925819614629174706175)",
+            ),
+            ("\"hello\" + name", "(hello + name)"),
+            (
+                "\"hello ${name}, you are ${age + 1}\"",
+                "\"hello ${name}, you are ${(age + 1)}\"",
+            ),
+            ("\"${a}${f(\"b\")}\" + c", "(\"${a}${f(b)}\" + c)"),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(parse(source).to_string(), expected, "source: {}", source);
+        }
     }
 
-    fn parse_return_statement(&mut self) -> Option<Box<ReturnStatement>> {
-        unimplemented!()
+    #[test]
+    fn test_statements() {
+        let tests = [
+            ("declare x = 5;", "declare x = 5"),
+            ("declare y = true;", "declare y = true"),
+            ("declare foo = bar * 2;", "declare foo = (bar * 2)"),
+            ("return 5;", "return 5"),
+            ("return x == y;", "return (x == y)"),
+            ("return add(1, 2);", "return add(1, 2)"),
+            ("while x < 5 { x; }", "while (x < 5) x"),
+            ("for c in \"abc\" { c }", "for c in abc c"),
+            ("for i in 0..n + 1 { i }", "for i in (0 .. (n + 1)) i"),
+            ("while true { break; continue }", "while true breakcontinue"),
+            ("for x in a { if x { break; } }", "for x in a si x break"),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(parse(source).to_string(), expected, "source: {}", source);
+        }
+    }
+
+    #[test]
+    fn test_if_and_proc_expressions() {
+        let tests = [
+            ("if (x < y) { x }", "si (x < y) x"),
+            ("if x < y { x } else { y }", "si (x < y) x sino y"),
+            ("proc(x, y) { x + y; }", "proc(x, y) (x + y)"),
+            ("proc() { return 1; }", "proc() return 1"),
+            ("proc(x) { x }(5)", "proc(x) x(5)"),
+            (
+                "declare add = proc(a, b) { a + b };",
+                "declare add = proc(a, b) (a + b)",
+            ),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(parse(source).to_string(), expected, "source: {}", source);
+        }
+    }
+
+    #[test]
+    fn test_parsed_nodes() {
+        let program = parse("declare answer = 42;");
+        let Statement::Let(statement) = &program.statements[0] else {
+            panic!("expected a let statement, got {:?}", program.statements[0]);
+        };
+
+        assert_eq!(statement.name.value, "answer");
+        let Expression::Integer(integer) = statement.value.as_ref() else {
+            panic!("expected an integer, got {:?}", statement.value);
+        };
+        assert_eq!(integer.value, IntegerValue::Small(42));
+        assert_eq!(integer.token.literal, "42");
+
+        let program = parse("99999999999999999999;");
+        let Statement::Expression(statement) = &program.statements[0] else {
+            panic!(
+                "expected an expression statement, got {:?}",
+                program.statements[0]
+            );
+        };
+        let Expression::Integer(integer) = statement.expression.as_ref() else {
+            panic!("expected an integer, got {:?}", statement.expression);
+        };
+        let big = BigInt::parse_bytes(b"99999999999999999999", 10).unwrap();
+        assert_eq!(integer.value, IntegerValue::Big(big));
+    }
+
+    #[test]
+    fn test_to_tree() {
+        let program =
+            parse("declare f = proc(x) { if x > 1 { return x * 2; } else { [x, -1] } }; f(3);");
+        let expected = "\
+Program
+  Let f
+    Proc(x)
+      If
+        Infix >
+          Identifier x
+          Integer 1
+        Then
+          Return
+            Infix *
+              Identifier x
+              Integer 2
+        Else
+          Array
+            Identifier x
+            Prefix -
+              Integer 1
+  Call
+    Identifier f
+    Integer 3
+";
+
+        assert_eq!(program.to_tree(), expected);
+    }
+
+    #[test]
+    fn test_doc_comments_are_kept_on_declarations() {
+        let program = parse(
+            "/// Doubles a number.\n\
+            declare double = proc(x) { x * 2 };\n\
+            // not documentation\n\
+            declare y = 1;",
+        );
+
+        let Statement::Let(double) = &program.statements[0] else {
+            panic!("expected a let statement, got {:?}", program.statements[0]);
+        };
+        assert_eq!(double.token.doc_comments, vec!["Doubles a number."]);
+        let Statement::Let(y) = &program.statements[1] else {
+            panic!("expected a let statement, got {:?}", program.statements[1]);
+        };
+        assert!(y.token.doc_comments.is_empty());
+    }
+
+    #[test]
+    fn test_parser_errors() {
+        let tests = [
+            (
+                "declare = 5;",
+                "expected next token to be IDENT, got ASSIGN instead",
+            ),
+            (
+                "declare x 5;",
+                "expected next token to be ASSIGN, got INT instead",
+            ),
+            (
+                "proc(1) { }",
+                "expected next token to be IDENT, got INT instead",
+            ),
+            (
+                "(1 + 2",
+                "expected next token to be RPAREN, got EOF instead",
+            ),
+            ("* 5", "no prefix parse function for * found"),
+            ("0x;", "could not parse 0x as an integer"),
+            (
+                "[1, 2",
+                "expected next token to be RBRACKET, got EOF instead",
+            ),
+            (
+                "a[1:2:3]",
+                "expected next token to be RBRACKET, got COLON instead",
+            ),
+            ("declare x = \"open;", "unterminated string"),
+            ("declare x = 1 # 2;", "unknown start of token: #"),
+            (
+                "\"a ${x y}\"",
+                "expected next token to be STRINGEND, got IDENT instead",
+            ),
+            ("declare x = /* 5;", "unterminated block comment"),
+            ("1 + 2 = 3;", "invalid assignment target"),
+            ("\"${}\";", "empty interpolation"),
+            ("\"a ${x} b ${ }\";", "empty interpolation"),
+            ("x + 1 += 2;", "invalid assignment target"),
+            ("f() = 1;", "invalid assignment target"),
+            ("m.1;", "expected next token to be IDENT, got INT instead"),
+            ("break;", "`break` outside of a loop"),
+            (
+                "while true { proc() { continue; } }",
+                "`continue` outside of a loop",
+            ),
+            (
+                "for 1 in x { }",
+                "expected next token to be IDENT, got INT instead",
+            ),
+            (
+                "for x of y { }",
+                "expected next token to be IN, got IDENT instead",
+            ),
+            (
+                "{\"a\" 1}",
+                "expected next token to be COLON, got INT instead",
+            ),
+        ];
+
+        for (source, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(source.to_owned()));
+            parser.parse_program();
+
+            assert_eq!(parser.errors()[0].message, expected, "source: {}", source);
+        }
+    }
+
+    #[test]
+    fn test_node_spans() {
+        let program = parse("declare x = 1; add(x, 2) * 3");
+
+        assert_eq!(
+            program.statements[0].span(),
+            Span::new(Position::new(0, 1, 1), Position::new(13, 1, 14))
+        );
+        assert_eq!(
+            program.statements[1].span(),
+            Span::new(Position::new(15, 1, 16), Position::new(28, 1, 29))
+        );
+
+        let Statement::Expression(statement) = &program.statements[1] else {
+            panic!("expected an expression statement");
+        };
+        let Expression::Infix(infix) = statement.expression.as_ref() else {
+            panic!("expected an infix expression");
+        };
+        assert_eq!(
+            infix.left.span(),
+            Span::new(Position::new(15, 1, 16), Position::new(24, 1, 25))
+        );
+    }
+
+    #[test]
+    fn test_error_recovery_collects_every_error() {
+        let source = "declare = 5; declare y = 10; declare z 1; return y * ;y + 1;";
+        let mut parser = Parser::new(Lexer::new(source.to_owned()));
+        let program = parser.parse_program();
+
+        let messages: Vec<String> = parser.errors().into_iter().map(|e| e.message).collect();
+        assert_eq!(
+            messages,
+            [
+                "expected next token to be IDENT, got ASSIGN instead",
+                "expected next token to be ASSIGN, got INT instead",
+                "no prefix parse function for ; found",
+            ]
+        );
+        assert_eq!(program.to_string(), "declare y = 10(y + 1)");
+    }
+
+    #[test]
+    fn test_error_recovery_inside_blocks() {
+        let tests = [
+            (
+                "declare f = proc(x) { declare = x; x * 2 }; f(2);",
+                "declare f = proc(x) (x * 2)f(2)",
+                1,
+            ),
+            ("if (x { 1 } declare y = 2;", "declare y = 2", 1),
+            ("declare a = 1 + ) declare b = 2;", "declare b = 2", 1),
+            ("declare c = * 3 proc(x) { x }", "proc(x) x", 1),
+            ("proc() { 1 + 1", "proc() (1 + 1)", 1),
+            ("proc() { declare x = }; 1", "proc() 1", 1),
+            (
+                "while true { declare y = 1 + }; declare z = 2;",
+                "while true declare z = 2",
+                1,
+            ),
+        ];
+
+        for (source, expected, error_count) in tests {
+            let mut parser = Parser::new(Lexer::new(source.to_owned()));
+            let program = parser.parse_program();
+
+            assert_eq!(program.to_string(), expected, "source: {}", source);
+            assert_eq!(
+                parser.errors().len(),
+                error_count,
+                "source: {}, errors: {:#?}",
+                source,
+                parser.errors()
+            );
+        }
     }
 }
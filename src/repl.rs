@@ -1,38 +1,399 @@
-use clap::Parser;
+use clap::Subcommand;
 use colored::Colorize;
-use std::io::{stdin, stdout, Write};
+use rustyline::{
+    completion::{Completer, Pair},
+    error::ReadlineError,
+    highlight::Highlighter,
+    Context, Editor,
+};
+use rustyline_derive::{Helper, Hinter, Validator};
+use std::{borrow::Cow, path::PathBuf, rc::Rc};
 
-#[derive(Parser)]
+use crate::{
+    ast::Program,
+    builtins::BUILTINS,
+    environment::{Env, Environment},
+    evaluator::evaluate,
+    highlight::{colorize, highlight},
+    lexer::Lexer,
+    object::Object,
+    parser::Parser,
+    token::{keywords, TokenType},
+};
+
+/// The nel interpreter. Starts a REPL when no subcommand is given.
+#[derive(clap::Parser)]
+#[clap(name = "nel", version, about)]
 pub struct Cli {
-    pub file: Option<std::path::PathBuf>,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Lex, parse and evaluate a script
+    Run {
+        /// Path to the script, or `-` to read it from stdin
+        file: String,
+        /// Arguments passed to the script, available as `args`
+        #[clap(last = true)]
+        args: Vec<String>,
+    },
+}
+
+#[derive(Helper, Hinter, Validator)]
+struct ReplHelper {
+    env: Env,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| unicode_ident::is_xid_continue(*c))
+            .last()
+            .map_or(pos, |(i, _)| i);
+        let word = &line[start..pos];
+
+        let mut names: Vec<String> = if line.starts_with(':') && !line[..start].contains(' ') {
+            COMMANDS.iter().map(|c| c.to_string()).collect()
+        } else {
+            let mut names: Vec<String> = keywords()
+                .into_keys()
+                .chain(BUILTINS.iter().map(|(name, _)| *name))
+                .map(|name| name.to_owned())
+                .collect();
+            names.extend(
+                self.env
+                    .borrow()
+                    .bindings()
+                    .into_iter()
+                    .map(|(name, _)| name),
+            );
+            names
+        };
+        names.retain(|name| name.starts_with(word));
+        names.sort();
+        names.dedup();
+
+        let candidates = names
+            .into_iter()
+            .map(|name| Pair {
+                display: name.clone(),
+                replacement: name,
+            })
+            .collect();
+
+        return Ok((start, candidates));
+    }
 }
 
-fn read_line(placeholder: &str) -> String {
-    let mut s = String::new();
-    print!("{}", format!("{} ", placeholder).blue());
-    let _ = stdout().flush();
-    stdin()
-        .read_line(&mut s)
-        .expect("Did not enter a correct string");
-    if let Some('\n') = s.chars().next_back() {
-        s.pop();
+impl Highlighter for ReplHelper {
+    fn highlight_prompt<'b, 's: 'b, 'p: 'b>(
+        &'s self,
+        prompt: &'p str,
+        _default: bool,
+    ) -> Cow<'b, str> {
+        Cow::Owned(prompt.blue().to_string())
     }
-    if let Some('\r') = s.chars().next_back() {
-        s.pop();
+
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        Cow::Owned(highlight(line))
     }
 
-    return s;
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        true
+    }
 }
 
+/// `~/.nel_history`, or `None` when the home directory is unknown.
+fn history_path() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    return Some(PathBuf::from(home).join(".nel_history"));
+}
+
+const COMMANDS: [&str; 7] = ["tokens", "ast", "type", "env", "load", "reset", "help"];
+
+const HELP: &str = "\
+:tokens <source>  print the tokens produced by the lexer
+:ast <source>     print the syntax tree produced by the parser
+:type <source>    evaluate <source> and print the type of the result
+:env              list the bindings declared in this session
+:load <file>      run a script in this session
+:reset            forget every binding declared in this session
+:help             show this message
+exit              leave the REPL
+
+Ctrl-C cancels a pending multi-line input, Ctrl-R searches the history
+and Tab completes keywords and declared names.";
+
 pub fn start_rpl() {
-    let mut scanned = vec![""];
+    let mut env = Environment::new();
+    let mut buffer = String::new();
+
+    let mut editor = Editor::<ReplHelper>::new();
+    editor.set_helper(Some(ReplHelper {
+        env: Rc::clone(&env),
+    }));
+
+    let history = history_path();
+    if let Some(path) = &history {
+        // A missing history file just means this is the first session.
+        let _ = editor.load_history(path);
+    }
+
+    loop {
+        let placeholder = if buffer.is_empty() { "> " } else { "... " };
+        let input = match editor.readline(placeholder) {
+            Ok(input) => input,
+            Err(ReadlineError::Interrupted) => {
+                if !buffer.is_empty() {
+                    buffer.clear();
+                    println!("{}", "(input cancelled)".dimmed());
+                }
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(error) => {
+                eprintln!("{}: {}", "error".red().bold(), error);
+                break;
+            }
+        };
+
+        if buffer.is_empty() {
+            if input == "exit" {
+                break;
+            }
+
+            if input.trim().is_empty() {
+                continue;
+            }
+
+            if let Some(command) = input.trim().strip_prefix(':') {
+                add_history_entry(&mut editor, &history, &input);
+                run_command(command, &mut env);
+                if let Some(helper) = editor.helper_mut() {
+                    helper.env = Rc::clone(&env);
+                }
+                continue;
+            }
+        }
+
+        if !buffer.is_empty() {
+            buffer.push('\n');
+        }
+        buffer.push_str(&input);
+
+        if !is_complete(&buffer) {
+            continue;
+        }
+
+        add_history_entry(&mut editor, &history, &buffer);
+        execute(&buffer, "<repl>", &env);
+        buffer.clear();
+    }
+}
+
+/// Adds `entry` to the history and appends it to the history file right
+/// away, so a crash or a killed terminal does not lose the session.
+fn add_history_entry(editor: &mut Editor<ReplHelper>, history: &Option<PathBuf>, entry: &str) {
+    editor.add_history_entry(entry);
+
+    if let Some(path) = history {
+        if let Err(error) = editor.append_history(path) {
+            eprintln!(
+                "{}: could not save history: {}",
+                "error".red().bold(),
+                error
+            );
+        }
+    }
+}
+
+/// Whether `source` can be run as is, or whether the REPL should keep
+/// reading lines because a `{`, `(` or string literal is still open.
+fn is_complete(source: &str) -> bool {
+    let mut lexer = Lexer::new(source.to_owned());
+    let mut depth = 0;
 
     loop {
-        let input = read_line("> ");
-        println!("You typed: {}", input);
+        let token = lexer.next_token();
+        match token.token_type {
+            TokenType::LBRACE
+            | TokenType::LBRACKET
+            | TokenType::LPAREN
+            | TokenType::STRINGSTART => depth += 1,
+            TokenType::RBRACE | TokenType::RBRACKET | TokenType::RPAREN | TokenType::STRINGEND => {
+                depth -= 1
+            }
+            // Unterminated strings and comments may still be closed.
+            TokenType::ILLEGAL if token.literal.ends_with('"') || token.literal == "/*" => {
+                return false
+            }
+            TokenType::EOF => return depth <= 0,
+            _ => {}
+        }
+    }
+}
+
+fn run_command(command: &str, env: &mut Env) {
+    let (name, argument) = match command.split_once(' ') {
+        Some((name, argument)) => (name, argument.trim()),
+        None => (command, ""),
+    };
+
+    match name {
+        "tokens" => {
+            let mut lexer = Lexer::new(argument.to_owned());
+            loop {
+                let token = lexer.next_token();
+                println!(
+                    "{:<14} {:<12} {}:{}",
+                    format!("{:?}", token.token_type),
+                    format!("{:?}", token.literal),
+                    token.span.start.line,
+                    token.span.start.column
+                );
+                if token.token_type == TokenType::EOF {
+                    break;
+                }
+            }
+        }
+        "ast" => {
+            if let Some(program) = parse(argument, "<repl>") {
+                print!("{}", program.to_tree());
+            }
+        }
+        "type" => {
+            if let Some(program) = parse(argument, "<repl>") {
+                // A scratch scope, so that `:type declare x = 1` declares nothing.
+                match evaluate(&program, &Environment::new_enclosed(env)) {
+                    Object::Error(message) => eprintln!("{}: {}", "error".red().bold(), message),
+                    result => println!("{:?}", result.object_type()),
+                }
+            }
+        }
+        "env" => {
+            for (name, value) in env.borrow().bindings() {
+                println!("{} = {}", name.bold(), colorize(&value));
+            }
+        }
+        "load" => match std::fs::read_to_string(argument) {
+            Ok(source) => execute(&source, argument, env),
+            Err(error) => eprintln!(
+                "{}: could not read {}: {}",
+                "error".red().bold(),
+                argument,
+                error
+            ),
+        },
+        "reset" => {
+            *env = Environment::new();
+            println!("{}", "(environment cleared)".dimmed());
+        }
+        "help" => println!("{}", HELP),
+        _ => eprintln!(
+            "{}: unknown command :{}, try :help",
+            "error".red().bold(),
+            name
+        ),
+    }
+}
+
+/// Parses `source`, printing its diagnostics if it has any.
+fn parse(source: &str, file_name: &str) -> Option<Program> {
+    let lexer = Lexer::new(source.to_owned());
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+
+    if !parser.errors().is_empty() {
+        for error in parser.errors() {
+            eprint!("{}", error.render(source, file_name));
+        }
+        return None;
+    }
+
+    return Some(program);
+}
+
+/// Parses and evaluates `source` against the REPL environment, printing the
+/// resulting value or the diagnostics that stopped it from running.
+fn execute(source: &str, file_name: &str, env: &Env) {
+    let program = match parse(source, file_name) {
+        Some(program) => program,
+        None => return,
+    };
+
+    match evaluate(&program, env) {
+        Object::Error(message) => eprintln!("{}: {}", "error".red().bold(), message),
+        Object::Null => {}
+        result => println!("{}", colorize(&result)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_complete() {
+        let tests = [
+            ("declare x = 5;", true),
+            ("declare f = proc(x) {", false),
+            ("declare f = proc(x) {\n  x * 2\n}", true),
+            ("add(1,", false),
+            ("add(1,\n2)", true),
+            ("if (x) { proc() { 1 }", false),
+            ("\"unterminated", false),
+            ("\"done\"", true),
+            ("r#\"raw\nstill \" open", false),
+            ("r#\"raw\nclosed\"#", true),
+            ("\"total: ${add(1,\n", false),
+            ("\"total: ${add(1,\n2)}\"", true),
+            ("/* still /* open */", false),
+            ("1 // comment", true),
+            ("1 + 2 }", true),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(is_complete(source), expected, "source: {:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_completion() {
+        let env = Environment::new();
+        env.borrow_mut()
+            .set("declared".to_owned(), Object::Integer(1));
+        env.borrow_mut().set("other".to_owned(), Object::Integer(2));
+
+        let helper = ReplHelper { env };
+        let history = rustyline::history::History::new();
+        let context = Context::new(&history);
+
+        let tests = [
+            ("dec", 0, vec!["declare", "declared"]),
+            ("1 + ot", 4, vec!["other"]),
+            ("if (t", 4, vec!["true"]),
+            (":lo", 1, vec!["load"]),
+            ("p", 0, vec!["pop", "proc", "push"]),
+            ("zzz", 0, vec![]),
+        ];
+
+        for (line, expected_start, expected) in tests {
+            let (start, candidates) = helper.complete(line, line.len(), &context).unwrap();
+            let candidates: Vec<String> = candidates.into_iter().map(|c| c.replacement).collect();
 
-        if input == "exit" {
-            break;
+            assert_eq!(start, expected_start, "line: {}", line);
+            assert_eq!(candidates, expected, "line: {}", line);
         }
     }
 }
@@ -2,40 +2,108 @@ use std::collections::HashMap;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum TokenType {
+    AND,
     ASSIGN,
+    BREAK,
+    COLON,
     COMMA,
+    CONTINUE,
     DIVISION,
+    DIVISIONASSIGN,
+    DOT,
     ELSE,
     EOF,
     EQ,
     FALSE,
+    FLOAT,
+    FOR,
     FUNCTION,
     GT,
+    GTEQ,
     IDENT,
     IF,
     ILLEGAL,
+    IN,
     INT,
     LBRACE,
+    LBRACKET,
     LET,
     LPAREN,
     LT,
+    LTEQ,
     MINUS,
+    MINUSASSIGN,
+    MODULO,
     MULTIPLICATION,
+    MULTIPLICATIONASSIGN,
     NEGATION,
     NOTEQ,
+    OR,
     PLUS,
+    PLUSASSIGN,
+    RANGE,
     RETURN,
     RPAREN,
     RBRACE,
+    RBRACKET,
     SEMICOLON,
     STRING,
+    STRINGEND,
+    STRINGMIDDLE,
+    STRINGSTART,
     TRUE,
+    WHILE,
+}
+
+/// A location in the source: byte offset plus 1-based line and column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn new(offset: usize, line: usize, column: usize) -> Position {
+        return Position {
+            offset,
+            line,
+            column,
+        };
+    }
+}
+
+impl Default for Position {
+    fn default() -> Position {
+        return Position::new(0, 1, 1);
+    }
+}
+
+/// The half-open range of source covered by a token or node.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Span {
+        return Span { start, end };
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn merge(&self, other: Span) -> Span {
+        return Span::new(self.start.min(other.start), self.end.max(other.end));
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
     pub literal: String,
+    pub span: Span,
+    /// The `///` doc comment lines written directly before this token.
+    pub doc_comments: Vec<String>,
 }
 
 impl Token {
@@ -43,11 +111,13 @@ impl Token {
         return Token {
             token_type,
             literal,
+            span: Span::default(),
+            doc_comments: vec![],
         };
     }
 }
 
-pub fn lookup_token_type(literal: &String) -> TokenType {
+pub fn keywords() -> HashMap<&'static str, TokenType> {
     let mut keywords = HashMap::new();
     keywords.insert("false", TokenType::FALSE);
     keywords.insert("proc", TokenType::FUNCTION);
@@ -56,9 +126,38 @@ pub fn lookup_token_type(literal: &String) -> TokenType {
     keywords.insert("else", TokenType::ELSE);
     keywords.insert("declare", TokenType::LET);
     keywords.insert("true", TokenType::TRUE);
+    keywords.insert("while", TokenType::WHILE);
+    keywords.insert("for", TokenType::FOR);
+    keywords.insert("in", TokenType::IN);
+    keywords.insert("break", TokenType::BREAK);
+    keywords.insert("continue", TokenType::CONTINUE);
+    return keywords;
+}
 
-    match keywords.get(literal.as_str()) {
-        Some(token_type) => token_type.clone(),
-        None => TokenType::IDENT,
-    }
+/// Every operator the lexer recognizes. When several match at the same
+/// position the longest one wins, so `<=` is never lexed as `<` and `=`.
+pub fn operators() -> Vec<(&'static str, TokenType)> {
+    return vec![
+        ("==", TokenType::EQ),
+        ("!=", TokenType::NOTEQ),
+        ("<=", TokenType::LTEQ),
+        (">=", TokenType::GTEQ),
+        ("&&", TokenType::AND),
+        ("||", TokenType::OR),
+        ("+=", TokenType::PLUSASSIGN),
+        ("-=", TokenType::MINUSASSIGN),
+        ("*=", TokenType::MULTIPLICATIONASSIGN),
+        ("/=", TokenType::DIVISIONASSIGN),
+        ("..", TokenType::RANGE),
+        (".", TokenType::DOT),
+        ("=", TokenType::ASSIGN),
+        ("!", TokenType::NEGATION),
+        ("<", TokenType::LT),
+        (">", TokenType::GT),
+        ("+", TokenType::PLUS),
+        ("-", TokenType::MINUS),
+        ("*", TokenType::MULTIPLICATION),
+        ("%", TokenType::MODULO),
+        ("/", TokenType::DIVISION),
+    ];
 }